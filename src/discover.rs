@@ -0,0 +1,97 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use glob::Pattern;
+
+/// Folders in a Thunderbird profile, which contain one directory for each mail account.
+const ACCOUNT_FOLDERS: [&str; 2] = ["ImapMail", "Mail"];
+
+/// Rules to decide which of the found mailbox files are used.
+#[derive(Debug, Default)]
+pub struct Filter {
+    /// Keep only the inbox at top level of each account.
+    pub inbox_only: bool,
+    /// Keep only files matching at least one of these patterns, unless empty.
+    pub include: Vec<Pattern>,
+    /// Drop files matching any of these patterns.
+    pub exclude: Vec<Pattern>,
+}
+
+impl Filter {
+    /// Build a filter from glob pattern strings, such as `ImapMail/*/INBOX.msf` .
+    ///
+    /// # Errors
+    ///
+    /// If any of the patterns is not a valid glob pattern.
+    pub fn new(inbox_only: bool, include: &[String], exclude: &[String]) -> Result<Self> {
+        let compile = |patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|p| Pattern::new(p).with_context(|| format!("Invalid pattern: {p}")))
+                .collect::<Result<Vec<_>>>()
+        };
+
+        Ok(Self {
+            inbox_only,
+            include: compile(include)?,
+            exclude: compile(exclude)?,
+        })
+    }
+
+    /// Check a mailbox file given as path relative to the profile directory. A `*` in patterns
+    /// matches across directories too, so `*/INBOX.msf` matches the inbox of every IMAP account.
+    fn matches(&self, file: &Path) -> bool {
+        if self.inbox_only {
+            let is_inbox = file
+                .file_name()
+                .is_some_and(|name| name.eq_ignore_ascii_case("inbox.msf"));
+            // Inbox at top level of an account, as in "ImapMail/imap.googlemail.com/INBOX.msf" .
+            if !is_inbox || file.components().count() != 3 {
+                return false;
+            }
+        }
+
+        (self.include.is_empty() || self.include.iter().any(|p| p.matches_path(file)))
+            && !self.exclude.iter().any(|p| p.matches_path(file))
+    }
+}
+
+/// Find all mailbox files of all accounts in a Thunderbird profile.
+///
+/// Both account folders `ImapMail` and `Mail` are searched recursively for .msf-files. The
+/// resulting paths are relative to the `profile` directory and sorted within each account folder.
+pub fn discover(profile: &Path, filter: &Filter) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for folder in ACCOUNT_FOLDERS {
+        find_mailbox_files(&profile.join(folder), &mut files);
+    }
+
+    files
+        .into_iter()
+        .filter_map(|file| file.strip_prefix(profile).map(Path::to_path_buf).ok())
+        .filter(|file| filter.matches(file))
+        .collect()
+}
+
+/// Collect all mailbox .msf-files in given directory and its subdirectories.
+///
+/// Thunderbird stores subfolders of a mailbox in a directory named after the parent mailbox with
+/// a ".sbd" extension, so a recursive search finds every folder of an account.
+pub fn find_mailbox_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut paths: Vec<PathBuf> = entries.filter_map(|e| e.ok().map(|e| e.path())).collect();
+    paths.sort();
+
+    for path in paths {
+        if path.is_dir() {
+            find_mailbox_files(&path, files);
+        } else if path.extension().is_some_and(|ext| ext == "msf") {
+            files.push(path);
+        }
+    }
+}
@@ -0,0 +1,36 @@
+//! Lookup count of unread Thunderbird mails.
+//!
+//! This is the library behind the `peepbird` commandline application. It reads Thunderbird
+//! mailbox .msf-files directly, so Thunderbird is not required to be running or even installed.
+//!
+//! A [`Profile`] is the users Thunderbird profile folder, which contains the mailbox files of all
+//! accounts. Each [`Mailbox`] is a single .msf-file with the counts of its folder. Counts of
+//! multiple mailboxes are collected in a [`Summary`] .
+//!
+//! ```no_run
+//! use peepbird::{Field, Filter, Mailbox, Profile, Summary};
+//!
+//! let profile = Profile::discover()?;
+//! let inbox = Mailbox::from_path(profile.path().join("ImapMail/imap.googlemail.com"));
+//! println!("{} unread", inbox.unread()?);
+//!
+//! let mut summary = Summary::new(Field::Unread);
+//! summary.set_accounts(profile.accounts()?);
+//! for file in profile.mailboxes(&Filter::default()) {
+//!     summary.add_mailbox(file.clone(), &Mailbox::from_path(profile.path().join(file)))?;
+//! }
+//! println!("{} unread in {} folders", summary.total, summary.mailboxes.len());
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+mod discover;
+mod mailbox;
+mod mork;
+mod profile;
+mod summary;
+
+pub use discover::Filter;
+pub use mailbox::{Field, Mailbox};
+pub use mork::FolderInfo;
+pub use profile::{Account, Profile};
+pub use summary::{HostCount, MailboxCount, Summary, SCHEMA_VERSION};
@@ -0,0 +1,214 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::mork::FolderInfo;
+
+/// Default filenames of the inbox in a mailbox folder, in order of priority.
+const INBOX_FILENAMES: [&str; 2] = ["Inbox.msf", "INBOX.msf"];
+
+/// Kind of messages to count, as stored in the folder information of a mailbox file.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Field {
+    /// Unread messages.
+    #[default]
+    Unread,
+    /// All messages.
+    Total,
+}
+
+impl Field {
+    /// Name of the column in the folder information row of a Mork mailbox file. Thunderbird
+    /// stores the unread count under the misleading name `numNewMsgs` .
+    #[must_use]
+    pub fn column(self) -> &'static str {
+        match self {
+            Self::Unread => "numNewMsgs",
+            Self::Total => "numMsgs",
+        }
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Unread => "unread",
+            Self::Total => "total",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// A single Thunderbird mailbox .msf-file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mailbox {
+    file: PathBuf,
+}
+
+impl Mailbox {
+    /// Create a mailbox from path to its .msf-file.
+    ///
+    /// The path can also be a mailbox folder of an account. Thunderbird mailbox folders contain
+    /// several .msf-files, so it's searched for an existing `Inbox.msf` or `INBOX.msf` to use.
+    /// If none is found, then the path is used as is. The file is not read before any count is
+    /// requested.
+    pub fn from_path(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let file = if path.is_dir() {
+            INBOX_FILENAMES
+                .iter()
+                .map(|name| path.join(name))
+                .find(|inbox| inbox.is_file())
+                .unwrap_or(path)
+        } else {
+            path
+        };
+
+        Self { file }
+    }
+
+    /// Path to the .msf-file.
+    #[must_use]
+    pub fn file(&self) -> &Path {
+        &self.file
+    }
+
+    /// Name of the folder derived from its filename, such as "INBOX" for `INBOX.msf` . Names of
+    /// IMAP folders in `ImapMail` are decoded from modified UTF-7, so `Entw&APw-rfe.msf` is named
+    /// "Entwürfe". Local folders keep their name as is.
+    #[must_use]
+    pub fn name(&self) -> String {
+        let stem = self
+            .file
+            .file_stem()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let is_imap = self
+            .file
+            .components()
+            .any(|component| component.as_os_str() == "ImapMail");
+        if is_imap {
+            decode_modified_utf7(&stem).unwrap_or(stem)
+        } else {
+            stem
+        }
+    }
+
+    /// Read the current folder information from the mailbox file.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be read or is not a valid Mork document.
+    pub fn folder_info(&self) -> Result<FolderInfo> {
+        FolderInfo::read(&self.file)
+    }
+
+    /// Read count of messages of given kind from the mailbox file.
+    ///
+    /// Thunderbird .msf mailbox files are an outdated format called "Mork". The file is parsed
+    /// for its folder information, which includes the current count of unread and all messages.
+    /// The requested `field` decides which of them is returned. Large files are only read
+    /// from their end as far as needed.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be read or parsed, or the count is not a valid number.
+    pub fn count(&self, field: Field) -> Result<u32> {
+        FolderInfo::read_columns(&self.file, &[field.column()])?
+            .number(field.column())
+            .with_context(|| format!("Failed to count mailbox: {}", self.file.display()))
+    }
+
+    /// Read count of unread messages from the mailbox file.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be read or parsed, or the count is not a valid number.
+    pub fn unread(&self) -> Result<u32> {
+        self.count(Field::Unread)
+    }
+}
+
+/// Decode a folder name in modified UTF-7, which IMAP uses for characters other than ASCII.
+///
+/// Thunderbird names the files of IMAP folders just like the server does. Characters between `&`
+/// and `-` are UTF-16 encoded in base64 with `,` instead of `/` , and `&-` stands for a literal
+/// `&` . Returns `None` if the name is not valid in this encoding, such as when a run leaves
+/// unused bits or decodes to nothing or to a NUL character.
+fn decode_modified_utf7(name: &str) -> Option<String> {
+    let mut output = String::with_capacity(name.len());
+    let mut rest = name;
+
+    while let Some(start) = rest.find('&') {
+        output.push_str(&rest[..start]);
+        let end = start + 1 + rest[start + 1..].find('-')?;
+        let encoded = &rest[start + 1..end];
+        rest = &rest[end + 1..];
+
+        if encoded.is_empty() {
+            output.push('&');
+            continue;
+        }
+
+        let mut bits: u32 = 0;
+        let mut count = 0;
+        let mut units = Vec::new();
+        for c in encoded.bytes() {
+            let value = match c {
+                b'A'..=b'Z' => c - b'A',
+                b'a'..=b'z' => c - b'a' + 26,
+                b'0'..=b'9' => c - b'0' + 52,
+                b'+' => 62,
+                b',' => 63,
+                _ => return None,
+            };
+            bits = ((bits << 6) | u32::from(value)) & 0x3F_FFFF;
+            count += 6;
+            if count >= 16 {
+                count -= 16;
+                units.push(u16::try_from((bits >> count) & 0xFFFF).ok()?);
+            }
+        }
+        if count >= 6 || bits & ((1 << count) - 1) != 0 || units.is_empty() {
+            return None;
+        }
+
+        let decoded = String::from_utf16(&units).ok()?;
+        if decoded.contains('\0') {
+            return None;
+        }
+        output.push_str(&decoded);
+    }
+    output.push_str(rest);
+
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn name_decodes_modified_utf7() {
+        let name = |file: &str| Mailbox::from_path(file).name();
+        assert_eq!(name("ImapMail/imap.x/Entw&APw-rfe.msf"), "Entwürfe");
+        assert_eq!(
+            name("ImapMail/imap.x/&BB4EQgQ,BEAEMAQyBDsENQQ9BD0ESwQ1-.msf"),
+            "Отправленные"
+        );
+        assert_eq!(name("ImapMail/imap.x/Tom &- Jerry.msf"), "Tom & Jerry");
+        assert_eq!(name("Mail/Local Folders/Inbox.msf"), "Inbox");
+        assert_eq!(name("Mail/Local Folders/Q&A.msf"), "Q&A");
+        assert_eq!(name("Mail/Local Folders/R&D-Team.msf"), "R&D-Team");
+        assert_eq!(name("A&B-C.msf"), "A&B-C");
+        assert_eq!(name("ImapMail/x/A&B-C.msf"), "A&B-C");
+        assert_eq!(name("ImapMail/x/R&D-Team.msf"), "R&D-Team");
+        assert_eq!(name("ImapMail/x/R&AAA-x.msf"), "R&AAA-x");
+    }
+}
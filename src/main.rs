@@ -1,74 +1,30 @@
+mod notification;
+mod parallel;
+mod template;
+mod watch;
+
 use std::{
+    collections::HashSet,
     env, fmt, fs,
+    io::{self, Write},
+    num::NonZeroUsize,
     path::{Path, PathBuf},
-    u32,
+    process::ExitCode,
+    thread,
+    time::Duration,
 };
 
 use anyhow::{anyhow, Context, Result};
-use clap::Parser;
-use serde_derive::Deserialize;
+use clap::{Parser, ValueEnum};
+use notification::Notifier;
+use peepbird::{Field, Filter, HostCount, Mailbox, MailboxCount, Profile, Summary};
+use serde_derive::{Deserialize, Serialize};
 
-/// Get number of unread messages from a Thunderbird mailbox file.
-///
-/// Thunderbird .msf mailbox files are an outdated format called "Mork". An documentation can be
-/// found at <https://github.com/KevinGoodsell/mork-converter/blob/master/doc/mork-format.txt>
-///
-/// The literal sequence `(^A2=)` points to the number of unread mails, where `^A2` is a reference
-/// for the key name. It's associated value can be found on the right side of the equal sign `=` .
-/// This key/value combination appears many times in the file, but only the last occurrence is the
-/// actual current value for total unread mails for the mailbox. The value is in hexadecimal format
-/// and will be converted to integer.
-fn mailbox_count_unread(mailbox_path: &Path) -> Result<u32, anyhow::Error> {
-    // We are looking for "(^A2=0)", where ^A2 is a reference to the key name and the hex
-    // number after "=" is the value of how many unread mails. But we need to read the last
-    // matching entry in entire file.
-    let unread = u32::from_str_radix(
-        fs::read_to_string(mailbox_path)
-            .with_context(|| format!("Failed to read mailbox: {}", mailbox_path.display()))?
-            .rsplit_once("(^A2=")
-            .unwrap_or_default()
-            .1
-            .split_once(')')
-            .unwrap_or_default()
-            .0,
-        16,
-    )
-    .unwrap_or(0);
-
-    Ok(unread)
-}
+/// Exit code if the total count is outside the range given by `min` and `max` options.
+const EXIT_OUT_OF_RANGE: u8 = 3;
 
-/// Lookup "Path=" key in "profiles.ini" inside Thunderbird main folder.
-///
-/// In the Thunderbird user data folder is a configuration file "profiles.ini". This file includes
-/// name of the default user profile. For simplicity it's assumed the first "Path=" key is the
-/// entry we are looking for. Take it's value on the right side after the equal sign "=".
-fn find_default_thunderbird_profile() -> Result<PathBuf, anyhow::Error> {
-    let thunderbird_dir = Path::new("~/.thunderbird");
-    match fullpath(&thunderbird_dir.join("profiles.ini")) {
-        Some(path) => {
-            let document = fs::read_to_string(path)?;
-            let profile = document
-                .split_once("Path=")
-                .unwrap_or_default()
-                .1
-                .split_once('\n')
-                .unwrap_or_default()
-                .0;
-
-            if profile.is_empty() {
-                Err(anyhow!(
-                    "No default profile found in Thunderbird profiles.ini."
-                ))
-            } else {
-                Ok(thunderbird_dir.join(profile))
-            }
-        }
-        None => Err(anyhow!(
-            "Could not find default Thunderbird profiles.ini file."
-        )),
-    }
-}
+/// Exit code if any mailbox file or remote host could not be counted.
+const EXIT_UNREADABLE: u8 = 4;
 
 /// Resolve all parts of path and make it absolute.
 ///
@@ -81,11 +37,54 @@ pub fn fullpath(file: &Path) -> Option<PathBuf> {
         .ok()
 }
 
+/// Check if path contains any special character of a glob pattern.
+fn is_glob_pattern(file: &Path) -> bool {
+    file.to_string_lossy().contains(['*', '?', '['])
+}
+
+/// Find all existing paths matching the glob pattern.
+///
+/// Relative patterns are matched starting from point of `profile` directory, and the resulting
+/// paths are relative to it as well. Tilde character "~" is expanded to users home directory.
+fn expand_glob_pattern(profile: &Path, pattern: &Path) -> Result<Vec<PathBuf>> {
+    let expanded = PathBuf::from(shellexpand::tilde(&pattern.display().to_string()).to_string());
+    let full = if expanded.is_absolute() {
+        expanded.display().to_string()
+    } else {
+        let base = glob::Pattern::escape(&profile.display().to_string());
+        Path::new(&base).join(&expanded).display().to_string()
+    };
+
+    let paths = glob::glob(&full)
+        .with_context(|| format!("Invalid pattern: {}", pattern.display()))?
+        .filter_map(Result::ok)
+        .map(|path| match path.strip_prefix(profile) {
+            Ok(relative) if !expanded.is_absolute() => relative.to_path_buf(),
+            _ => path,
+        })
+        .collect();
+
+    Ok(paths)
+}
+
+/// Output document of a waybar custom module with `"return-type": "json"` .
+#[derive(Serialize, Debug)]
+struct WaybarModule {
+    text: String,
+    tooltip: String,
+    class: &'static str,
+}
+
 /// Current configuration state of entire application.
 #[derive(Debug)]
 struct App {
     arguments: Arguments,
     settings: Settings,
+    /// Input files as given by the user, before they are resolved in `settings` .
+    inputs: Vec<PathBuf>,
+    /// Input files from arguments or config, before any are added by other options. Kept to
+    /// resolve them again in watch mode.
+    given: Option<Vec<PathBuf>>,
 }
 
 impl App {
@@ -106,6 +105,8 @@ impl App {
                 config: fullpath(&config_path),
                 ..Default::default()
             },
+            inputs: Vec::new(),
+            given: None,
         }
     }
 
@@ -131,6 +132,10 @@ impl App {
         if let Some(value) = cfg.profile {
             self.settings.profile.replace(value);
         }
+        // An empty name as written by `dump_config` means no name is set.
+        if let Some(value) = cfg.profile_name.filter(|name| !name.is_empty()) {
+            self.settings.profile_name.replace(value);
+        }
         if let Some(value) = cfg.dump_config {
             self.settings.dump_config.replace(value);
         }
@@ -155,6 +160,67 @@ impl App {
         if let Some(value) = cfg.after {
             self.settings.after.replace(value);
         }
+        // Empty templates as written by `dump_config` mean no template is set.
+        if let Some(value) = cfg.template.filter(|text| !text.is_empty()) {
+            self.settings.template.replace(value);
+        }
+        if let Some(value) = cfg.template_total.filter(|text| !text.is_empty()) {
+            self.settings.template_total.replace(value);
+        }
+        if let Some(value) = cfg.badge {
+            self.settings.badge.replace(value);
+        }
+        if let Some(value) = cfg.hosts {
+            self.settings.hosts.replace(value);
+        }
+        if let Some(value) = cfg.watch {
+            self.settings.watch.replace(value);
+        }
+        if let Some(value) = cfg.interval {
+            self.settings.interval.replace(value);
+        }
+        if let Some(value) = cfg.format {
+            self.settings.format.replace(value);
+        }
+        if let Some(value) = cfg.field {
+            self.settings.field.replace(value);
+        }
+        if let Some(value) = cfg.all {
+            self.settings.all.replace(value);
+        }
+        if let Some(value) = cfg.inbox_only {
+            self.settings.inbox_only.replace(value);
+        }
+        if let Some(value) = cfg.include {
+            self.settings.include.replace(value);
+        }
+        if let Some(value) = cfg.exclude {
+            self.settings.exclude.replace(value);
+        }
+        if let Some(value) = cfg.ignore_unmatched {
+            self.settings.ignore_unmatched.replace(value);
+        }
+        if let Some(value) = cfg.notify {
+            self.settings.notify.replace(value);
+        }
+        if let Some(value) = cfg.notify_threshold {
+            self.settings.notify_threshold.replace(value);
+        }
+        if let Some(value) = cfg.quiet {
+            self.settings.quiet.replace(value);
+        }
+        if let Some(value) = cfg.min {
+            self.settings.min.replace(value);
+        }
+        if let Some(value) = cfg.max {
+            self.settings.max.replace(value);
+        }
+        if let Some(value) = cfg.skip_errors {
+            self.settings.skip_errors.replace(value);
+        }
+        if let Some(value) = cfg.jobs {
+            self.settings.jobs.replace(value);
+        }
     }
 
     /// Overwrite each applications Settings fields by given arguments. Arguments are parsed with
@@ -165,8 +231,14 @@ impl App {
         if !self.arguments.files.is_empty() {
             self.settings.files.replace(self.arguments.files.clone());
         }
+        // A profile given on commandline by path or name replaces both kinds from config.
         if let Some(value) = self.arguments.profile.clone() {
             self.settings.profile.replace(value);
+            self.settings.profile_name = None;
+        }
+        if let Some(value) = self.arguments.profile_name.clone() {
+            self.settings.profile_name.replace(value);
+            self.settings.profile = None;
         }
 
         // NOTE: self.settings.config field should have been updated before this function, right
@@ -195,50 +267,226 @@ impl App {
         if let Some(value) = self.arguments.after.clone() {
             self.settings.after.replace(value);
         }
+        if let Some(value) = self.arguments.template.clone() {
+            self.settings.template.replace(value);
+        }
+        if let Some(value) = self.arguments.template_total.clone() {
+            self.settings.template_total.replace(value);
+        }
 
         if self.arguments.location {
             self.settings.location.replace(true);
         }
+        if self.arguments.badge {
+            self.settings.badge.replace(true);
+        }
+        if !self.arguments.hosts.is_empty() {
+            self.settings.hosts.replace(self.arguments.hosts.clone());
+        }
+        if self.arguments.watch {
+            self.settings.watch.replace(true);
+        }
+        if let Some(value) = self.arguments.interval {
+            self.settings.interval.replace(value);
+        }
+        if let Some(value) = self.arguments.format {
+            self.settings.format.replace(value);
+        }
+        if let Some(value) = self.arguments.field {
+            self.settings.field.replace(value.into());
+        }
+        if self.arguments.all {
+            self.settings.all.replace(true);
+        }
+        if self.arguments.inbox_only {
+            self.settings.inbox_only.replace(true);
+        }
+        if !self.arguments.include.is_empty() {
+            self.settings
+                .include
+                .replace(self.arguments.include.clone());
+        }
+        if !self.arguments.exclude.is_empty() {
+            self.settings
+                .exclude
+                .replace(self.arguments.exclude.clone());
+        }
+        if self.arguments.ignore_unmatched {
+            self.settings.ignore_unmatched.replace(true);
+        }
+        if self.arguments.notify {
+            self.settings.notify.replace(true);
+        }
+        if let Some(value) = self.arguments.notify_threshold {
+            self.settings.notify_threshold.replace(value);
+        }
+        if self.arguments.quiet {
+            self.settings.quiet.replace(true);
+        }
+        if let Some(value) = self.arguments.min {
+            self.settings.min.replace(value);
+        }
+        if let Some(value) = self.arguments.max {
+            self.settings.max.replace(value);
+        }
+        if self.arguments.skip_errors {
+            self.settings.skip_errors.replace(true);
+        }
+        if let Some(value) = self.arguments.jobs {
+            self.settings.jobs.replace(value);
+        }
+        // Remote hosts are queried with this option, so their own configuration cannot keep
+        // them running or silent, ask further hosts or dump the configuration instead.
+        if self.arguments.once {
+            self.settings.watch.replace(false);
+            self.settings.interval = None;
+            self.settings.notify.replace(false);
+            self.settings.quiet.replace(false);
+            self.settings.hosts =
+                (!self.arguments.hosts.is_empty()).then(|| self.arguments.hosts.clone());
+            self.settings
+                .dump_config
+                .replace(self.arguments.dump_config);
+        }
+    }
+
+    /// Replace each input file given by the user as glob pattern with all matching paths. This
+    /// must run before any files are added by other options, as their paths are never patterns.
+    /// A path that exists as it is, is not a pattern either, because folders like `[Gmail].sbd`
+    /// contain special characters. If a pattern matches nothing, it's an error unless the
+    /// `ignore_unmatched` option is enabled. With `badge` option, the input files are replaced
+    /// anyway and not looked at.
+    fn update_files_from_patterns(&mut self) -> Result<(), anyhow::Error> {
+        if self.settings.badge.unwrap_or(false) {
+            return Ok(());
+        }
+        let Some(files) = self.settings.files.as_ref() else {
+            return Ok(());
+        };
+        if !files.iter().any(|f| is_glob_pattern(f)) {
+            return Ok(());
+        }
+        let profile = self.profile()?;
+
+        let mut expanded = Vec::with_capacity(files.len());
+        for f in files {
+            let literal = PathBuf::from(shellexpand::tilde(&f.display().to_string()).to_string());
+            if !is_glob_pattern(f) || profile.path().join(literal).exists() {
+                expanded.push(f.clone());
+                continue;
+            }
+            let found = expand_glob_pattern(profile.path(), f)?;
+            if found.is_empty() && !self.settings.ignore_unmatched.unwrap_or(false) {
+                return Err(anyhow!("No mailbox files match pattern: {}", f.display()));
+            }
+            expanded.extend(found);
+        }
+        self.settings.files.replace(expanded);
+
+        Ok(())
+    }
+
+    /// Add all mailbox files found in the users profile directory to the input files. This is
+    /// only done if `all` option is enabled in applications `Settings` . The found files are
+    /// relative to the profile, just like input files given by the user.
+    fn update_files_from_discovery(&mut self) -> Result<(), anyhow::Error> {
+        if self.settings.all.unwrap_or(false) {
+            let profile = self.profile()?;
+            let filter = Filter::new(
+                self.settings.inbox_only.unwrap_or(false),
+                &self.settings.include.clone().unwrap_or_default(),
+                &self.settings.exclude.clone().unwrap_or_default(),
+            )?;
+            self.settings
+                .files
+                .get_or_insert_with(Vec::new)
+                .extend(profile.mailboxes(&filter));
+        }
+
+        Ok(())
+    }
+
+    /// Remove each mailbox file that is already included earlier. The same file could be
+    /// given by the user and found again with `all` option, or given twice through different
+    /// paths. Only the first occurrence is kept to not count it multiple times.
+    fn dedup_files(&mut self) {
+        let Some(files) = self.settings.files.as_mut() else {
+            return;
+        };
+        let mut seen = HashSet::new();
+        let (inputs, unique): (Vec<PathBuf>, Vec<PathBuf>) = self
+            .inputs
+            .drain(..)
+            .zip(files.drain(..))
+            .filter(|(_, file)| seen.insert(file.clone()))
+            .unzip();
+        self.inputs = inputs;
+        *files = unique;
+    }
+
+    /// Check if only remote hosts should be queried. This is the case if no local input files are
+    /// given, but at least one host. A profile is not required then.
+    fn is_remote_only(&self) -> bool {
+        self.settings.files.is_none()
+            && self
+                .settings
+                .hosts
+                .as_ref()
+                .is_some_and(|hosts| !hosts.is_empty())
+    }
+
+    /// Get the users profile from Settings, either by its name or directory. Lookup the default
+    /// Thunderbird profile if none is specified.
+    fn profile(&self) -> Result<Profile, anyhow::Error> {
+        if let Some(name) = &self.settings.profile_name {
+            Profile::named(name)
+        } else if let Some(profile) = &self.settings.profile {
+            fullpath(profile).map(Profile::from_path).ok_or_else(|| {
+                anyhow!(
+                    "Specified profile file could not be found: {}",
+                    profile.display()
+                )
+            })
+        } else {
+            Profile::discover()
+        }
+    }
+
+    /// Replace input files with the mailboxes Thunderbird counts for its own badge. This is only
+    /// done if `badge` option is enabled in applications `Settings` .
+    fn update_files_from_badge(&mut self) -> Result<(), anyhow::Error> {
+        if self.settings.badge.unwrap_or(false) {
+            let mailboxes = self.profile()?.badge_mailboxes()?;
+            self.settings.files.replace(
+                mailboxes
+                    .iter()
+                    .map(|mailbox| mailbox.file().to_path_buf())
+                    .collect(),
+            );
+        }
+
+        Ok(())
     }
 
     /// Add user profile dir to each relative mailbox files. Each Thunderbird .msf input files that
     /// are relative paths will be expanded to absolute `fullpath` by joining it to the specified
     /// users `profile` directory from applications `Settings` .
     fn update_relative_files_with_profile(&mut self) -> Result<(), anyhow::Error> {
-        let profile: Option<PathBuf> = {
-            if let Some(profile) = self.settings.profile.as_mut() {
-                match fullpath(profile) {
-                    Some(file) => Some(file),
-                    None => {
-                        return Err(anyhow!(
-                            "Specified profile file could not be found: {}",
-                            profile.display()
-                        ))
-                    }
-                }
-            } else {
-                Some(find_default_thunderbird_profile()?)
-            }
-        };
+        if self.is_remote_only() {
+            return Ok(());
+        }
+        let profile = self.profile()?;
 
-        if let Some(p) = profile {
-            if self.settings.files.is_some() {
-                self.settings
-                    .files
-                    .as_mut()
-                    .unwrap()
-                    .iter_mut()
-                    .for_each(|f| {
-                        let d = p.join(f.clone());
-                        f.push(fullpath(&d).unwrap_or_default());
-                    });
+        if let Some(files) = self.settings.files.as_mut() {
+            self.inputs.clone_from(files);
+            files.iter_mut().for_each(|f| {
+                let d = profile.path().join(f.clone());
+                f.push(fullpath(&d).unwrap_or_default());
+            });
 
-                Ok(())
-            } else {
-                Err(anyhow!("No input files for mailboxes specified."))
-            }
+            Ok(())
         } else {
-            Err(anyhow!("Could not find a profile."))
+            Err(anyhow!("No input files for mailboxes specified."))
         }
     }
 
@@ -247,24 +495,272 @@ impl App {
     /// files. Search the directory for existing `Inbox.msf` or `INBOX.msf` filenames. Join the
     /// name to the mailbox path if any found.
     fn update_directory_files_with_default_filename(&mut self) {
-        self.settings
-            .files
-            .as_mut()
-            .unwrap()
-            .iter_mut()
-            .for_each(|f| {
-                if f.is_dir() {
-                    let inbox = f.join("Inbox.msf");
-                    if inbox.is_file() {
-                        f.push(inbox);
-                    } else {
-                        let inbox = f.join("INBOX.msf");
-                        if inbox.is_file() {
-                            f.push(inbox);
-                        }
-                    }
+        let Some(files) = self.settings.files.as_mut() else {
+            return;
+        };
+        files.iter_mut().for_each(|f| {
+            *f = Mailbox::from_path(f.clone()).file().to_path_buf();
+        });
+    }
+
+    /// Count unread messages of all mailboxes and hosts.
+    ///
+    /// Read each input files unread message count and query each remote host for its total
+    /// count. All individual counts are kept together with their location and added up to the
+    /// total count. The first failure aborts counting, unless `skip_errors` option is enabled.
+    /// Then the failure is reported to stderr and the location is left out of the result.
+    ///
+    /// Up to `jobs` mailboxes or hosts are processed at the same time, which defaults to the
+    /// number of available CPUs. The result is in order of input nevertheless. Each mailbox gets
+    /// the name and color of its account in the profile, if there is one.
+    fn count(&self) -> Result<Summary, anyhow::Error> {
+        let field = self.settings.field.unwrap_or_default();
+        let mut summary = Summary::new(field);
+        let jobs = match self.settings.jobs {
+            Some(jobs) if jobs > 0 => jobs,
+            _ => thread::available_parallelism().map_or(1, NonZeroUsize::get),
+        };
+        let skip = |result: Result<u32, anyhow::Error>| match result {
+            Err(e) if self.settings.skip_errors.unwrap_or(false) => {
+                eprintln!("Error: {e:#}");
+                Ok(())
+            }
+            result => result.map(|_| ()),
+        };
+
+        // Process each individual mailbox input and get count unread mails.
+        if let Some(files) = &self.settings.files {
+            let mailboxes: Vec<(&PathBuf, &PathBuf)> = self.inputs.iter().zip(files).collect();
+            let accounts = self
+                .profile()
+                .and_then(|profile| profile.accounts())
+                .unwrap_or_default();
+            summary.set_accounts(accounts);
+            let counts = parallel::map(&mailboxes, jobs, |(path, file)| {
+                MailboxCount::read((*path).clone(), &Mailbox::from_path(file), field)
+            });
+            for count in counts {
+                skip(count.map(|count| summary.push_mailbox(count)))?;
+            }
+        }
+
+        // Query each remote host for its total count of unread mails.
+        if let Some(hosts) = &self.settings.hosts {
+            let counts = parallel::map(hosts, jobs, |host| HostCount::query(host, field));
+            for count in counts {
+                skip(count.map(|count| summary.push_host(count)))?;
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// Decide the exit code of the application for given counts. Any location left out of the
+    /// counts due to `skip_errors` option has priority over a total count outside the range of
+    /// `min` and `max` options.
+    fn exit_code(&self, counts: &Summary) -> ExitCode {
+        let expected = self.settings.files.as_ref().map_or(0, Vec::len)
+            + self.settings.hosts.as_ref().map_or(0, Vec::len);
+        let below = self.settings.min.is_some_and(|min| counts.total < min);
+        let above = self.settings.max.is_some_and(|max| counts.total > max);
+
+        if counts.mailboxes.len() + counts.hosts.len() < expected {
+            ExitCode::from(EXIT_UNREADABLE)
+        } else if below || above {
+            ExitCode::from(EXIT_OUT_OF_RANGE)
+        } else {
+            ExitCode::SUCCESS
+        }
+    }
+
+    /// Build the text for total count from `template_total` . Without a template, the total
+    /// count is surrounded by `before` and `after` text. If `no_zero` option is enabled, then
+    /// `{total}` is empty for a count of '0'.
+    fn format_total(&self, counts: &Summary) -> String {
+        let template = self.settings.template_total.clone().unwrap_or_else(|| {
+            format!(
+                "{}{{total}}{}",
+                template::escape(&self.settings.before.clone().unwrap_or_default()),
+                template::escape(&self.settings.after.clone().unwrap_or_default())
+            )
+        });
+        let no_zero = self.settings.no_zero.unwrap_or(false);
+
+        let output = template::render(&template, |name| match name {
+            "total" if no_zero && counts.total == 0 => Some(String::new()),
+            "total" => Some(counts.total.to_string()),
+            "mailboxes" => Some(counts.mailboxes.len().to_string()),
+            _ => None,
+        });
+
+        if self.settings.trim.unwrap_or(false) {
+            output.trim().to_owned()
+        } else {
+            output
+        }
+    }
+
+    /// Build a line for each mailbox file and remote host from `template` , which defaults to
+    /// the count followed by its location. Remote hosts use the host name as `{path}` and
+    /// `{name}` , and have an empty `{account_name}` and `{account_color}` . Locations with a
+    /// count of '0' are left out, if `no_zero` option is enabled.
+    fn format_locations(&self, counts: &Summary) -> Vec<String> {
+        let template = self
+            .settings
+            .template
+            .clone()
+            .unwrap_or_else(|| String::from("{count} {file}"));
+        let no_zero = self.settings.no_zero.unwrap_or(false);
+        let line = |count: u32, path: &str, file: &str, name: &str, account: (&str, &str)| {
+            template::render(&template, |placeholder| match placeholder {
+                "count" => Some(count.to_string()),
+                "path" => Some(path.to_owned()),
+                "file" => Some(file.to_owned()),
+                "name" => Some(name.to_owned()),
+                "account_name" => Some(account.0.to_owned()),
+                "account_color" => Some(account.1.to_owned()),
+                "total" => Some(counts.total.to_string()),
+                "mailboxes" => Some(counts.mailboxes.len().to_string()),
+                _ => None,
+            })
+        };
+
+        let mailboxes = counts
+            .mailboxes
+            .iter()
+            .filter(|m| !(no_zero && m.count == 0))
+            .map(|m| {
+                line(
+                    m.count,
+                    &m.path.display().to_string(),
+                    &m.file.display().to_string(),
+                    &m.name,
+                    (
+                        m.account_name.as_deref().unwrap_or_default(),
+                        m.account_color.as_deref().unwrap_or_default(),
+                    ),
+                )
+            });
+        let hosts = counts
+            .hosts
+            .iter()
+            .filter(|h| !(no_zero && h.count == 0))
+            .map(|h| line(h.count, &h.host, &format!("{}:", h.host), &h.host, ("", "")));
+
+        mailboxes.chain(hosts).collect()
+    }
+
+    /// Print the counts of all mailboxes and hosts to stdout.
+    ///
+    /// The output depends on the `format` option. Plain text prints the total count as the last
+    /// line, preceded by a line for each individual count if `location` or `template` option is
+    /// given. The other formats print a single line of JSON. Nothing is printed at all, if
+    /// `quiet` option is enabled.
+    fn print_counts(&self, counts: &Summary) -> Result<(), anyhow::Error> {
+        if self.settings.quiet.unwrap_or(false) {
+            return Ok(());
+        }
+
+        let output = match self.settings.format.unwrap_or_default() {
+            Format::Plain => {
+                let mut lines = if self.settings.location.unwrap_or(false)
+                    || self.settings.template.is_some()
+                {
+                    self.format_locations(counts)
+                } else {
+                    Vec::new()
+                };
+                lines.push(self.format_total(counts));
+                lines.join("\n")
+            }
+            Format::Json => serde_json::to_string(counts)?,
+            Format::Waybar => serde_json::to_string(&WaybarModule {
+                text: self.format_total(counts),
+                tooltip: self.format_locations(counts).join("\n"),
+                class: if counts.total == 0 { "zero" } else { "unread" },
+            })?,
+        };
+
+        let mut stdout = io::stdout().lock();
+        if self.settings.no_newline.unwrap_or(false) {
+            write!(stdout, "{output}")?;
+        } else {
+            writeln!(stdout, "{output}")?;
+        };
+
+        // Status bars read the output line by line from a pipe, so don't keep it in the buffer
+        // while waiting for the next change in watch mode.
+        stdout.flush()?;
+
+        Ok(())
+    }
+
+    /// Resolve the input files given by the user to the mailbox files to count, including
+    /// files added by `badge` and `all` options. Each call starts again from the `given` input
+    /// files, so mailboxes added to the profile in the meantime are found too.
+    fn resolve_files(&mut self) -> Result<(), anyhow::Error> {
+        self.settings.files.clone_from(&self.given);
+        self.update_files_from_patterns()?;
+        self.update_files_from_badge()?;
+        self.update_files_from_discovery()?;
+        self.update_relative_files_with_profile()?;
+        self.update_directory_files_with_default_filename();
+        self.dedup_files();
+
+        Ok(())
+    }
+
+    /// Print counts again each time a mailbox file changes or the `interval` in seconds elapsed.
+    /// An `interval` alone enables watch mode as well, while '0' disables it. Errors while
+    /// counting are reported to stderr without ending the watch, as Thunderbird may be in the
+    /// middle of rewriting a file. If `notify` option is enabled, a desktop notification is sent
+    /// whenever new messages arrived.
+    ///
+    /// With `badge` or `all` option, the whole profile directory is watched and the mailbox files
+    /// are resolved again before each count, so new accounts and folders are included.
+    fn watch_counts(&mut self) -> Result<(), anyhow::Error> {
+        let files = self.settings.files.clone().unwrap_or_default();
+        let interval = self
+            .settings
+            .interval
+            .filter(|&secs| secs > 0)
+            .map(Duration::from_secs);
+        let profile = if self.settings.badge.unwrap_or(false) || self.settings.all.unwrap_or(false)
+        {
+            Some(self.profile()?.path().to_path_buf())
+        } else {
+            None
+        };
+
+        if interval.is_none() && files.is_empty() && profile.is_none() {
+            return Err(anyhow!(
+                "Nothing to watch without mailbox files, specify an interval."
+            ));
+        }
+
+        let mut notifier = self
+            .settings
+            .notify
+            .unwrap_or(false)
+            .then(|| Notifier::new(self.settings.notify_threshold.unwrap_or(1)));
+
+        watch::watch(&files, profile.as_deref(), interval, || {
+            let resolved = if profile.is_some() {
+                self.resolve_files()
+            } else {
+                Ok(())
+            };
+            let result = resolved.and_then(|()| self.count()).and_then(|counts| {
+                self.print_counts(&counts)?;
+                if let Some(notifier) = notifier.as_mut() {
+                    notifier.update(&counts)?;
                 }
+                Ok(())
             });
+            if let Err(e) = result {
+                eprintln!("Error: {e:#}");
+            }
+        })
     }
 }
 
@@ -276,9 +772,10 @@ struct Arguments {
     #[arg(help = "Path to one or multiple mailbox .msf-files. Either absolute\n\
                 or relative starting from point of user profile directory.\n\
                 Input FILES given as folders will be searched for any default\n\
-                filename to append.\n\
+                filename to append. Glob patterns match multiple FILES.\n\
                 Examples:\n\
                 \"Mail/pop3.live.com\"\n\
+                \"ImapMail/*/INBOX.msf\"\n\
                 \"~/.thunderbird/abcd.default/ImapMail/imap.googlemail.com/INBOX.msf\"")]
     files: Vec<PathBuf>,
 
@@ -291,6 +788,16 @@ struct Arguments {
     )]
     profile: Option<PathBuf>,
 
+    #[arg(
+        short = 'P',
+        long,
+        value_name = "NAME",
+        display_order = 5,
+        conflicts_with = "profile",
+        help = "Name of Thunderbird user profile as listed in profiles.ini"
+    )]
+    profile_name: Option<String>,
+
     #[arg(
         short = 'c',
         long,
@@ -308,6 +815,13 @@ struct Arguments {
     )]
     dump_config: bool,
 
+    #[arg(
+        long,
+        display_order = 25,
+        help = "Print JSON Schema of the json output format and exit"
+    )]
+    schema: bool,
+
     #[arg(
         short = 'C',
         long,
@@ -365,6 +879,238 @@ struct Arguments {
         help = "Display file path for each input mailbox"
     )]
     location: bool,
+
+    #[arg(
+        long,
+        value_name = "TEXT",
+        display_order = 91,
+        help = "Display a line for each input mailbox with placeholders\n\
+                {count}, {path}, {file}, {name}, {account_name},\n\
+                {account_color}, {total} and {mailboxes}.\n\
+                Default: \"{count} {file}\""
+    )]
+    template: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "TEXT",
+        display_order = 92,
+        help = "Text for total count with placeholders {total} and\n\
+                {mailboxes}. Replaces --before and --after"
+    )]
+    template_total: Option<String>,
+
+    #[arg(
+        long,
+        display_order = 100,
+        help = "Count the same mailboxes as Thunderbird does for its badge.\n\
+                Reads accounts and notification settings from prefs.js\n\
+                of the profile and ignores input FILES"
+    )]
+    badge: bool,
+
+    #[arg(
+        short = 'H',
+        long = "host",
+        value_name = "HOST",
+        display_order = 110,
+        help = "Add total count of peepbird on a remote machine over ssh.\n\
+                Can be given multiple times. Without input FILES, only\n\
+                remote hosts are counted"
+    )]
+    hosts: Vec<String>,
+
+    #[arg(
+        short = 'w',
+        long,
+        display_order = 120,
+        help = "Keep running and print counts again on each change of\n\
+                any mailbox file"
+    )]
+    watch: bool,
+
+    #[arg(
+        short = 'i',
+        long,
+        value_name = "SECS",
+        display_order = 130,
+        help = "In watch mode, also print counts again if no change\n\
+                happened for SECS seconds. Implies --watch"
+    )]
+    interval: Option<u64>,
+
+    #[arg(
+        short = 'f',
+        long,
+        value_name = "FORMAT",
+        display_order = 140,
+        help = "Output format. \"json\" includes every individual count,\n\
+                \"waybar\" is for a custom module with json return-type"
+    )]
+    format: Option<Format>,
+
+    #[arg(
+        short = 'F',
+        long,
+        value_name = "FIELD",
+        display_order = 150,
+        help = "Kind of messages to count in each mailbox"
+    )]
+    field: Option<FieldArg>,
+
+    #[arg(
+        short = 'A',
+        long,
+        visible_alias = "discover",
+        display_order = 160,
+        help = "Add all mailbox files found in ImapMail and Mail folders\n\
+                of the profile to input FILES"
+    )]
+    all: bool,
+
+    #[arg(
+        short = 'I',
+        long,
+        display_order = 170,
+        help = "With --all, only add the inbox of each account"
+    )]
+    inbox_only: bool,
+
+    #[arg(
+        long,
+        value_name = "PATTERN",
+        display_order = 180,
+        help = "With --all, only add mailbox files matching glob PATTERN.\n\
+                Relative to profile folder, \"*\" also matches \"/\".\n\
+                Can be given multiple times. Example: \"ImapMail/*\""
+    )]
+    include: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "PATTERN",
+        display_order = 190,
+        help = "With --all, do not add mailbox files matching glob PATTERN.\n\
+                Can be given multiple times. Example: \"*/Trash.msf\""
+    )]
+    exclude: Vec<String>,
+
+    #[arg(
+        long,
+        display_order = 200,
+        help = "Do not fail if a glob pattern in FILES matches nothing"
+    )]
+    ignore_unmatched: bool,
+
+    #[arg(
+        long,
+        display_order = 210,
+        help = "In watch mode, send a desktop notification when new\n\
+                messages arrive. Implies --watch"
+    )]
+    notify: bool,
+
+    #[arg(
+        long,
+        value_name = "NUM",
+        display_order = 220,
+        help = "Only notify once at least NUM new messages arrived since\n\
+                the last notification"
+    )]
+    notify_threshold: Option<u32>,
+
+    #[arg(
+        short = 'q',
+        long,
+        display_order = 230,
+        help = "Do not print anything to stdout. Use the exit code instead"
+    )]
+    quiet: bool,
+
+    #[arg(
+        long,
+        value_name = "NUM",
+        display_order = 240,
+        help = "Exit with code 3 if total count is less than NUM"
+    )]
+    min: Option<u32>,
+
+    #[arg(
+        long,
+        value_name = "NUM",
+        display_order = 250,
+        help = "Exit with code 3 if total count is greater than NUM"
+    )]
+    max: Option<u32>,
+
+    #[arg(
+        long,
+        display_order = 260,
+        help = "Leave out mailbox files and hosts that cannot be counted,\n\
+                instead of failing. Exits with code 4 after output"
+    )]
+    skip_errors: bool,
+
+    #[arg(
+        short = 'j',
+        long,
+        value_name = "NUM",
+        display_order = 270,
+        help = "Read up to NUM mailbox files and hosts at the same time.\n\
+                Default is '0' for number of CPUs"
+    )]
+    jobs: Option<usize>,
+
+    #[arg(
+        long,
+        display_order = 280,
+        help = "Print counts a single time, even if watch, interval, notify\n\
+                or quiet options are configured. Hosts and dump_config are\n\
+                only used from commandline"
+    )]
+    once: bool,
+}
+
+/// Style of output printed to stdout.
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+enum Format {
+    /// Total count as text, optionally preceded by each location.
+    #[default]
+    Plain,
+    /// Total and each individual count as JSON document.
+    Json,
+    /// JSON document with text, tooltip and class for waybar.
+    Waybar,
+}
+
+/// Commandline values for the `Field` of the library, which does not depend on `clap` .
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum FieldArg {
+    /// Unread messages.
+    Unread,
+    /// All messages.
+    Total,
+}
+
+impl From<FieldArg> for Field {
+    fn from(value: FieldArg) -> Self {
+        match value {
+            FieldArg::Unread => Self::Unread,
+            FieldArg::Total => Self::Total,
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Plain => "plain",
+            Self::Json => "json",
+            Self::Waybar => "waybar",
+        };
+        write!(f, "{name}")
+    }
 }
 
 /// Main configuration for app state and the base for user config file in TOML format.
@@ -372,6 +1118,7 @@ struct Arguments {
 struct Settings {
     files: Option<Vec<PathBuf>>,
     profile: Option<PathBuf>,
+    profile_name: Option<String>,
     config: Option<PathBuf>,
     dump_config: Option<bool>,
     no_config: Option<bool>,
@@ -381,6 +1128,42 @@ struct Settings {
     before: Option<String>,
     after: Option<String>,
     location: Option<bool>,
+    template: Option<String>,
+    template_total: Option<String>,
+    badge: Option<bool>,
+    hosts: Option<Vec<String>>,
+    watch: Option<bool>,
+    interval: Option<u64>,
+    format: Option<Format>,
+    field: Option<Field>,
+    all: Option<bool>,
+    inbox_only: Option<bool>,
+    include: Option<Vec<String>>,
+    exclude: Option<Vec<String>>,
+    ignore_unmatched: Option<bool>,
+    notify: Option<bool>,
+    notify_threshold: Option<u32>,
+    quiet: Option<bool>,
+    min: Option<u32>,
+    max: Option<u32>,
+    skip_errors: Option<bool>,
+    jobs: Option<usize>,
+}
+
+/// Format a list of strings as TOML array, with one item per line if there are multiple.
+fn toml_array(items: &[String]) -> String {
+    match items {
+        [] => String::from("[]"),
+        [item] => format!("[\"{item}\"]"),
+        _ => {
+            let mut output = String::from("[");
+            for item in items {
+                output.push_str(&format!("\n    \"{item}\","));
+            }
+            output.push_str("\n]");
+            output
+        }
+    }
 }
 
 /// Convert to TOML String, compatible with user config file format.
@@ -410,10 +1193,23 @@ impl fmt::Display for Settings {
             "\"{}\"",
             self.profile
                 .clone()
-                .unwrap_or(find_default_thunderbird_profile().unwrap_or_default())
+                .unwrap_or_else(|| match &self.profile_name {
+                    Some(name) => Profile::named(name)
+                        .map(|p| p.path().to_path_buf())
+                        .unwrap_or_default(),
+                    None => Profile::discover()
+                        .map(|p| p.path().to_path_buf())
+                        .unwrap_or_default(),
+                })
                 .display()
         ));
 
+        output.push_str("\nprofile_name = ");
+        output.push_str(&format!(
+            "\"{}\"",
+            self.profile_name.clone().unwrap_or_default()
+        ));
+
         output.push_str("\nconfig = ");
         output.push_str(&format!(
             "\"{}\"",
@@ -444,21 +1240,94 @@ impl fmt::Display for Settings {
         output.push_str("\nlocation = ");
         output.push_str(&self.location.unwrap_or_default().to_string());
 
+        output.push_str("\ntemplate = ");
+        output.push_str(&format!(
+            "\"{}\"",
+            self.template.clone().unwrap_or_default()
+        ));
+
+        output.push_str("\ntemplate_total = ");
+        output.push_str(&format!(
+            "\"{}\"",
+            self.template_total.clone().unwrap_or_default()
+        ));
+
+        output.push_str("\nbadge = ");
+        output.push_str(&self.badge.unwrap_or_default().to_string());
+
+        output.push_str("\nhosts = ");
+        output.push_str(&toml_array(&self.hosts.clone().unwrap_or_default()));
+
+        output.push_str("\nwatch = ");
+        output.push_str(&self.watch.unwrap_or_default().to_string());
+
+        output.push_str("\ninterval = ");
+        output.push_str(&self.interval.unwrap_or_default().to_string());
+
+        output.push_str("\nformat = ");
+        output.push_str(&format!("\"{}\"", self.format.unwrap_or_default()));
+
+        output.push_str("\nfield = ");
+        output.push_str(&format!("\"{}\"", self.field.unwrap_or_default()));
+
+        output.push_str("\nall = ");
+        output.push_str(&self.all.unwrap_or_default().to_string());
+
+        output.push_str("\ninbox_only = ");
+        output.push_str(&self.inbox_only.unwrap_or_default().to_string());
+
+        output.push_str("\ninclude = ");
+        output.push_str(&toml_array(&self.include.clone().unwrap_or_default()));
+
+        output.push_str("\nexclude = ");
+        output.push_str(&toml_array(&self.exclude.clone().unwrap_or_default()));
+
+        output.push_str("\nignore_unmatched = ");
+        output.push_str(&self.ignore_unmatched.unwrap_or_default().to_string());
+
+        output.push_str("\nnotify = ");
+        output.push_str(&self.notify.unwrap_or_default().to_string());
+
+        output.push_str("\nnotify_threshold = ");
+        output.push_str(&self.notify_threshold.unwrap_or(1).to_string());
+
+        output.push_str("\nquiet = ");
+        output.push_str(&self.quiet.unwrap_or_default().to_string());
+
+        // Without a limit there is no value to write, as TOML has no empty value for numbers.
+        if let Some(min) = self.min {
+            output.push_str(&format!("\nmin = {min}"));
+        }
+        if let Some(max) = self.max {
+            output.push_str(&format!("\nmax = {max}"));
+        }
+
+        output.push_str("\nskip_errors = ");
+        output.push_str(&self.skip_errors.unwrap_or_default().to_string());
+
+        output.push_str("\njobs = ");
+        output.push_str(&self.jobs.unwrap_or_default().to_string());
+
         write!(f, "{output}")
     }
 }
 
 /// Parse args, config and input files. Count sum and print to stdout.
 ///
-/// Parse arguments and user configuration to build a state. Read each input files unread message
-/// count and add up to total count. In final step, prepare the output string and print it to
-/// stdout.
-fn main() -> Result<(), anyhow::Error> {
+/// Parse arguments and user configuration to build a state. Then count and print the unread
+/// messages once, or keep doing so on each change in watch mode. The exit code tells if the
+/// total count is within range of `min` and `max` options, and if all mailboxes could be read.
+fn main() -> Result<ExitCode, anyhow::Error> {
     // Create application state, by parsing commandline arguments and loading user configuration file.
     // Arguments have higher priority and will overwrite default and user configuration.
-    let app = {
+    let mut app = {
         let mut app = App::new();
 
+        if app.arguments.schema {
+            println!("{}", serde_json::to_string_pretty(&Summary::schema())?);
+            return Ok(ExitCode::SUCCESS);
+        }
+
         if !app.arguments.no_config {
             match app.parse_config() {
                 Ok(settings) => {
@@ -476,65 +1345,38 @@ fn main() -> Result<(), anyhow::Error> {
         }
 
         app.update_settings_from_arguments();
+        app.given.clone_from(&app.settings.files);
 
-        match app.update_relative_files_with_profile() {
-            Ok(()) => (),
-            Err(e) => {
-                if app.settings.dump_config.unwrap_or(false) {
-                    println!("{}", app.settings);
-                }
-                return Err(e);
+        if let Err(e) = app.resolve_files() {
+            if app.settings.dump_config.unwrap_or(false) {
+                println!("{}", app.settings);
             }
+            return Err(e);
         }
 
-        app.update_directory_files_with_default_filename();
-
         app
     };
 
     if app.settings.dump_config.unwrap_or(false) {
         println!("{}", app.settings);
-        return Ok(());
+        return Ok(ExitCode::SUCCESS);
     }
 
-    let mut total_count: u32 = 0;
-
-    // Process each individual mailbox input and get count unread mails.
-    if let Some(files) = &app.settings.files {
-        for mailbox in files {
-            let count = mailbox_count_unread(mailbox)?;
-            total_count += count;
-            if app.settings.location.unwrap_or(false) {
-                if app.settings.no_zero.unwrap_or(false) && count == 0 {
-                    continue;
-                }
-                println!("{count} {}", mailbox.display());
+    if app.settings.watch.unwrap_or(false)
+        || app.settings.interval.is_some_and(|secs| secs > 0)
+        || app.settings.notify.unwrap_or(false)
+    {
+        app.watch_counts().map(|()| ExitCode::SUCCESS)
+    } else {
+        let counts = match app.count() {
+            Ok(counts) => counts,
+            Err(e) => {
+                eprintln!("Error: {e:?}");
+                return Ok(ExitCode::from(EXIT_UNREADABLE));
             }
-        }
-    }
-
-    let output = {
-        let before = app.settings.before.unwrap_or_default();
-        let after = app.settings.after.unwrap_or_default();
-        let output_total_count = if app.settings.no_zero.unwrap_or(false) && total_count == 0 {
-            String::new()
-        } else {
-            total_count.to_string()
         };
-        if app.settings.trim.unwrap_or(false) {
-            format!("{before}{output_total_count}{after}")
-                .trim()
-                .to_owned()
-        } else {
-            format!("{before}{output_total_count}{after}")
-        }
-    };
+        app.print_counts(&counts)?;
 
-    if app.settings.no_newline.unwrap_or(false) {
-        print!("{output}");
-    } else {
-        println!("{output}");
-    };
-
-    Ok(())
+        Ok(app.exit_code(&counts))
+    }
 }
@@ -0,0 +1,801 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use anyhow::{anyhow, Context, Result};
+
+/// Row scope of the single row in a mailbox file with summary information about the folder.
+const FOLDER_INFO_SCOPE: &str = "ns:msg:db:row:scope:dbfolderinfo:all";
+
+/// First bytes of every Mork document.
+const MORK_HEADER: &[u8] = b"// <!-- <mdb:mork";
+
+/// Files up to this size in bytes are always parsed as a whole, which is fast enough.
+const SCAN_THRESHOLD: u64 = 1024 * 1024;
+
+/// Size in bytes of each block read while scanning a file from its end.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Maximum bytes read from the end of a file, before scanning is given up for a full parse.
+const SCAN_LIMIT: usize = 16 * 1024 * 1024;
+
+/// Summary information about a mail folder, read from its Thunderbird .msf-file.
+///
+/// Thunderbird keeps a single "dbfolderinfo" row in each mailbox file, with cells such as
+/// `numMsgs` for count of all messages, `numNewMsgs` for count of unread messages (despite its
+/// name) or `folderName` . Cell values are stored as text, numbers in hexadecimal format.
+#[derive(Debug, Default)]
+pub struct FolderInfo {
+    cells: HashMap<String, String>,
+}
+
+impl FolderInfo {
+    /// Parse the Mork document of a mailbox file and collect the final state of its folder info.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be read or is not a Mork document with folder information.
+    pub fn read(path: &Path) -> Result<Self> {
+        let document = fs::read(path)
+            .with_context(|| format!("Failed to read mailbox: {}", path.display()))?;
+
+        Self::parse(&document)
+            .with_context(|| format!("Failed to parse mailbox: {}", path.display()))
+    }
+
+    /// Parse a Mork document and collect the final state of its folder info.
+    ///
+    /// # Errors
+    ///
+    /// If the document is not in Mork format or has no folder information.
+    pub fn parse(document: &[u8]) -> Result<Self> {
+        if !document.starts_with(MORK_HEADER) {
+            return Err(anyhow!("Not a Mork document."));
+        }
+
+        let mut parser = Parser::new(document);
+        parser.parse_document();
+
+        let cells = parser
+            .into_cells()
+            .ok_or_else(|| anyhow!("No folder information found."))?;
+
+        Ok(Self { cells })
+    }
+
+    /// Read the final state of the given columns from the folder info of a mailbox file.
+    ///
+    /// Large files are not parsed as a whole. Thunderbird appends each update of the folder info
+    /// to the end of the file, so it's scanned backwards in chunks for the last cell of each
+    /// column, and only the rest of the file from there is parsed. The dictionary of column
+    /// names is taken from the start of the file. Whenever the result would be uncertain, the
+    /// whole file is parsed like with `read` instead.
+    ///
+    /// Only the requested columns are guaranteed to be up to date. Other cells may be from the
+    /// initial folder info at start of the file, which is fine for rarely changing ones like
+    /// `folderName` .
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be read or is not a Mork document with folder information.
+    pub fn read_columns(path: &Path, columns: &[&str]) -> Result<Self> {
+        let scanned = File::open(path)
+            .and_then(|mut file| {
+                let len = file.metadata()?.len();
+                if len <= SCAN_THRESHOLD {
+                    Ok(None)
+                } else {
+                    Self::scan(&mut file, len, columns)
+                }
+            })
+            .with_context(|| format!("Failed to read mailbox: {}", path.display()))?;
+
+        match scanned {
+            Some(info) => Ok(info),
+            None => Self::read(path),
+        }
+    }
+
+    /// Read the last cells of `columns` by scanning the file backwards from its end. Returns
+    /// `None` if this is not possible with certainty and the whole file needs to be parsed.
+    fn scan<R>(file: &mut R, len: u64, columns: &[&str]) -> io::Result<Option<Self>>
+    where
+        R: Read + Seek,
+    {
+        // Thunderbird writes the dictionary of column names and the initial folder info at the
+        // start of the file, followed by the messages.
+        let mut head = vec![0; CHUNK_SIZE];
+        file.seek(SeekFrom::Start(0))?;
+        file.read_exact(&mut head)?;
+        if !head.starts_with(MORK_HEADER) {
+            return Ok(None);
+        }
+        let mut parser = Parser::new(&head);
+        parser.parse_document();
+        let Parser {
+            columns: dictionary,
+            atoms,
+            folder_info: initial,
+            ..
+        } = parser;
+
+        let Some(ids) = columns
+            .iter()
+            .map(|&name| {
+                let (id, _) = dictionary.iter().find(|(_, column)| *column == name)?;
+                Some(format!("^{id}"))
+            })
+            .collect::<Option<Vec<_>>>()
+        else {
+            return Ok(None);
+        };
+        // Cells are searched by column id, such as `(^A2=` or `(^A2^` for `numNewMsgs` .
+        let patterns: Vec<Vec<u8>> = ids.iter().map(|id| format!("({id}").into_bytes()).collect();
+
+        // Chunks are kept in order of reading, from the end of the file towards its start. Found
+        // positions are offsets in the file.
+        let longest = patterns.iter().map(Vec::len).max().unwrap_or_default();
+        let mut chunks: Vec<Vec<u8>> = Vec::new();
+        let mut found: Vec<Option<u64>> = vec![None; patterns.len()];
+        let mut offset = len;
+        while found.iter().any(Option::is_none) {
+            if offset == 0 || len - offset >= SCAN_LIMIT as u64 {
+                return Ok(None);
+            }
+            let size = usize::try_from(offset).map_or(CHUNK_SIZE, |o| o.min(CHUNK_SIZE));
+            offset -= size as u64;
+
+            // The start of the chunk read before is appended, to find cells crossing the border.
+            let mut chunk = vec![0; size];
+            file.seek(SeekFrom::Start(offset))?;
+            file.read_exact(&mut chunk)?;
+            if let Some(next) = chunks.last() {
+                chunk.extend_from_slice(&next[..next.len().min(longest)]);
+            }
+
+            for (pattern, position) in patterns.iter().zip(&mut found) {
+                if position.is_none() {
+                    let end = chunk.len().min(size + pattern.len());
+                    *position = rfind_cell(&chunk[..end], pattern).map(|pos| offset + pos as u64);
+                }
+            }
+
+            chunk.truncate(size);
+            chunks.push(chunk);
+        }
+        chunks.reverse();
+        let tail = chunks.concat();
+
+        // Parse from the start of the row with the earliest of the found cells. The row must
+        // declare its scope itself, as the enclosing table is not known.
+        let first = found
+            .into_iter()
+            .flatten()
+            .min()
+            .and_then(|pos| usize::try_from(pos - offset).ok())
+            .unwrap_or_default();
+        let Some(row) = tail[..first].iter().rposition(|&c| c == b'[') else {
+            return Ok(None);
+        };
+        if find(&tail[row..], b"~abort~").is_some() {
+            return Ok(None);
+        }
+
+        // Continue with the initial folder info, as if the file was parsed up to the row. The
+        // requested columns are left out, so they are only found if set in the rest of the file.
+        // Atoms of the initial cells are resolved already, as the rest of the file may define
+        // atoms with the same ids again.
+        let mut initial: HashMap<String, Value> = initial
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(column, value)| match value {
+                Value::Atom(id) => (
+                    column,
+                    Value::Literal(atoms.get(&id).cloned().unwrap_or_default()),
+                ),
+                literal @ Value::Literal(_) => (column, literal),
+            })
+            .collect();
+        for (id, name) in ids.iter().zip(columns) {
+            initial.remove(id);
+            initial.remove(*name);
+        }
+        let mut parser = Parser {
+            columns: dictionary,
+            folder_info: Some(initial),
+            ..Parser::new(&tail[row..])
+        };
+        parser.parse_document();
+
+        // An atom defined between the start and the scanned rest of the file is not known.
+        let is_unknown_atom = |key: &str| {
+            parser
+                .folder_info
+                .as_ref()
+                .and_then(|cells| cells.get(key))
+                .is_some_and(
+                    |value| matches!(value, Value::Atom(id) if !parser.atoms.contains_key(id)),
+                )
+        };
+        if ids
+            .iter()
+            .zip(columns)
+            .any(|(id, name)| is_unknown_atom(id) || is_unknown_atom(name))
+        {
+            return Ok(None);
+        }
+
+        let cells = parser.into_cells().unwrap_or_default();
+        if !columns.iter().all(|&name| cells.contains_key(name)) {
+            return Ok(None);
+        }
+
+        Ok(Some(Self { cells }))
+    }
+
+    /// Get text value of a cell by its column name.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.cells.get(name).map(String::as_str)
+    }
+
+    /// Get numeric value of a cell by its column name. Thunderbird leaves out cells it never
+    /// had to set, which are counted as '0' therefore.
+    ///
+    /// # Errors
+    ///
+    /// If the value is not a hexadecimal number.
+    pub fn number(&self, name: &str) -> Result<u32> {
+        self.get(name).map_or(Ok(0), |value| {
+            u32::from_str_radix(value, 16)
+                .with_context(|| format!("Invalid number in column {name}: {value}"))
+        })
+    }
+}
+
+/// Value of a cell, either given directly or as reference to an atom in a dictionary.
+#[derive(Debug)]
+enum Value {
+    Literal(String),
+    Atom(String),
+}
+
+/// Sequential reader for the Mork format.
+///
+/// A documentation of the format can be found at
+/// <https://github.com/KevinGoodsell/mork-converter/blob/master/doc/mork-format.txt>
+///
+/// The whole document is read from start to end, as later rows and groups update the cells of
+/// earlier ones. Only cells of the folder info row are kept, all other rows are skipped over.
+/// Ids are hexadecimal and stored in upper case, to match references regardless of case.
+struct Parser<'a> {
+    input: &'a [u8],
+    pos: usize,
+    /// Column names by id, from dictionaries with `(a=c)` scope.
+    columns: HashMap<String, String>,
+    /// Atom values by id, from all other dictionaries.
+    atoms: HashMap<String, String>,
+    /// Raw cells of folder info row with unresolved column ids, if any found.
+    folder_info: Option<HashMap<String, Value>>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a [u8]) -> Self {
+        Self {
+            input,
+            pos: 0,
+            columns: HashMap::new(),
+            atoms: HashMap::new(),
+            folder_info: None,
+        }
+    }
+
+    /// Cells of the folder info row by column name, if any found. Column names and atom values
+    /// are resolved at the end, as their dictionaries may be updated after the rows referencing
+    /// them.
+    fn into_cells(self) -> Option<HashMap<String, String>> {
+        let raw_cells = self.folder_info?;
+        let cells = raw_cells
+            .into_iter()
+            .map(|(column, value)| {
+                let name = match column.strip_prefix('^') {
+                    Some(id) => self.columns.get(id).cloned().unwrap_or(column),
+                    None => column,
+                };
+                let value = match value {
+                    Value::Literal(text) => text,
+                    Value::Atom(id) => self.atoms.get(&id).cloned().unwrap_or_default(),
+                };
+                (name, value)
+            })
+            .collect();
+
+        Some(cells)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
+    }
+
+    fn rest(&self) -> &[u8] {
+        self.input.get(self.pos..).unwrap_or_default()
+    }
+
+    /// Skip whitespace and `//` comments up to the end of line.
+    fn skip_space(&mut self) {
+        while let Some(c) = self.peek() {
+            if c.is_ascii_whitespace() {
+                self.pos += 1;
+            } else if self.rest().starts_with(b"//") {
+                while self.peek().is_some_and(|c| c != b'\n') {
+                    self.pos += 1;
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Read an id, name or similar token up to the next delimiter.
+    fn token(&mut self) -> String {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| !c.is_ascii_whitespace() && !b"()[]{}<>=^:".contains(&c))
+        {
+            self.pos += 1;
+        }
+        String::from_utf8_lossy(&self.input[start..self.pos]).to_ascii_uppercase()
+    }
+
+    /// Read a literal value up to the unescaped closing parenthesis, which is consumed too.
+    ///
+    /// A backslash escapes the next character or continues the value on the next line, `$` is
+    /// followed by two hexadecimal digits for a single byte.
+    fn literal(&mut self) -> String {
+        let mut bytes = Vec::new();
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            match c {
+                b')' => break,
+                b'\\' => match self.peek() {
+                    Some(b'\r') => {
+                        self.pos += 1;
+                        if self.peek() == Some(b'\n') {
+                            self.pos += 1;
+                        }
+                    }
+                    Some(b'\n') => self.pos += 1,
+                    Some(next) => {
+                        bytes.push(next);
+                        self.pos += 1;
+                    }
+                    None => (),
+                },
+                b'$' => {
+                    let hex = self.rest().get(..2).unwrap_or_default();
+                    match std::str::from_utf8(hex)
+                        .ok()
+                        .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                    {
+                        Some(byte) => {
+                            bytes.push(byte);
+                            self.pos += 2;
+                        }
+                        None => bytes.push(c),
+                    }
+                }
+                _ => bytes.push(c),
+            }
+        }
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+
+    /// Read a scope after an id, either as `^ID` reference to a column name or a literal name.
+    fn scope(&mut self) -> Option<String> {
+        if self.peek() != Some(b':') {
+            return None;
+        }
+        self.pos += 1;
+        if self.peek() == Some(b'^') {
+            self.pos += 1;
+            let id = self.token();
+            Some(self.columns.get(&id).cloned().unwrap_or(id))
+        } else {
+            Some(self.token())
+        }
+    }
+
+    fn parse_document(&mut self) {
+        loop {
+            self.skip_space();
+            match self.peek() {
+                None => break,
+                Some(b'<') => self.parse_dict(),
+                Some(b'{') => self.parse_table(),
+                Some(b'[') => self.parse_row(None),
+                Some(b'@') => self.parse_group(),
+                Some(_) => self.pos += 1,
+            }
+        }
+    }
+
+    /// Read a dictionary `<(ID=value)...>` , optionally starting with a meta dictionary such as
+    /// `<(a=c)>` which declares all following aliases to be column names.
+    fn parse_dict(&mut self) {
+        self.pos += 1;
+        let mut is_columns = false;
+        loop {
+            self.skip_space();
+            match self.peek() {
+                None => break,
+                Some(b'>') => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(b'<') => {
+                    self.pos += 1;
+                    while let Some(c) = self.peek() {
+                        self.pos += 1;
+                        if c == b'>' {
+                            break;
+                        } else if c == b'(' {
+                            let key = self.token();
+                            self.pos += 1;
+                            let value = self.literal();
+                            if key == "A" && value == "c" {
+                                is_columns = true;
+                            }
+                        }
+                    }
+                }
+                Some(b'(') => {
+                    self.pos += 1;
+                    let id = self.token();
+                    self.pos += 1;
+                    let value = self.literal();
+                    if is_columns {
+                        self.columns.insert(id, value);
+                    } else {
+                        self.atoms.insert(id, value);
+                    }
+                }
+                Some(_) => self.pos += 1,
+            }
+        }
+    }
+
+    /// Read a table `{ID:scope {meta} [row]... rowref...}` . Rows without own scope belong to
+    /// the scope of the table.
+    fn parse_table(&mut self) {
+        self.pos += 1;
+        self.skip_space();
+        self.pos += usize::from(self.peek() == Some(b'-'));
+        self.token();
+        let scope = self.scope();
+        loop {
+            self.skip_space();
+            match self.peek() {
+                None => break,
+                Some(b'}') => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(b'{') => {
+                    self.pos += 1;
+                    self.skip_cells(b'}');
+                }
+                Some(b'[') => self.parse_row(scope.as_deref()),
+                Some(b'@') => self.parse_group(),
+                Some(_) => self.pos += 1,
+            }
+        }
+    }
+
+    /// Read a row `[ID:scope (column=value)...]` . A leading `-` on the id removes all previous
+    /// cells of that row before the new ones are added.
+    fn parse_row(&mut self, table_scope: Option<&str>) {
+        self.pos += 1;
+        self.skip_space();
+        let cut = self.peek() == Some(b'-');
+        self.pos += usize::from(cut);
+        self.token();
+        let scope = self.scope();
+
+        let is_folder_info = scope.as_deref().or(table_scope) == Some(FOLDER_INFO_SCOPE);
+        if !is_folder_info {
+            self.skip_cells(b']');
+            return;
+        }
+
+        let cells = self.folder_info.get_or_insert_with(HashMap::new);
+        if cut {
+            cells.clear();
+        }
+
+        loop {
+            self.skip_space();
+            match self.peek() {
+                None => break,
+                Some(b']') => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(b'[') => {
+                    self.pos += 1;
+                    self.skip_cells(b']');
+                }
+                Some(b'(') => {
+                    self.pos += 1;
+                    let column = if self.peek() == Some(b'^') {
+                        self.pos += 1;
+                        format!("^{}", self.token())
+                    } else {
+                        let start = self.pos;
+                        while self.peek().is_some_and(|c| c != b'=' && c != b'^') {
+                            self.pos += 1;
+                        }
+                        String::from_utf8_lossy(&self.input[start..self.pos]).into_owned()
+                    };
+                    let value = if self.peek() == Some(b'^') {
+                        self.pos += 1;
+                        let id = self.token();
+                        while self.peek().is_some_and(|c| c != b')') {
+                            self.pos += 1;
+                        }
+                        self.pos += 1;
+                        Value::Atom(id)
+                    } else {
+                        self.pos += 1;
+                        Value::Literal(self.literal())
+                    };
+                    if let Some(cells) = self.folder_info.as_mut() {
+                        cells.insert(column, value);
+                    }
+                }
+                Some(_) => self.pos += 1,
+            }
+        }
+    }
+
+    /// Skip over cells up to and including the closing `end` character.
+    fn skip_cells(&mut self, end: u8) {
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            if c == end {
+                break;
+            } else if c == b'(' {
+                // Skip the column, so an escaped parenthesis in the value is not mistaken as end.
+                while self.peek().is_some_and(|c| c != b'=' && c != b')') {
+                    self.pos += 1;
+                }
+                self.pos += 1;
+                if self.input.get(self.pos - 1) == Some(&b'=') {
+                    self.literal();
+                }
+            }
+        }
+    }
+
+    /// Read start or end marker of a group `@$${ID{@ ... @$$}ID}@` . The content of a group is
+    /// read like the rest of the document, unless it ends with an abort marker
+    /// `@$$}~abort~ID}@` , in which case it's skipped entirely.
+    fn parse_group(&mut self) {
+        if self.rest().starts_with(b"@$${") {
+            let content = self.pos;
+            let Some(end) = find(self.rest(), b"@$$}") else {
+                self.pos = self.input.len();
+                return;
+            };
+            let end = self.pos + end;
+            if self.input[end..].starts_with(b"@$$}~abort~") {
+                self.pos = end;
+            } else {
+                self.pos = content + find(self.rest(), b"{@").map_or(4, |p| p + 2);
+            }
+        } else if self.rest().starts_with(b"@$$}") {
+            self.pos += find(self.rest(), b"}@").map_or(self.rest().len(), |p| p + 2);
+        } else {
+            self.pos += 1;
+        }
+    }
+}
+
+/// Position of first occurrence of `needle` in `haystack` .
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Position of last cell starting with `column` in `haystack` , followed by its value. The
+/// check for the value avoids matching a longer column id with the same start.
+fn rfind_cell(haystack: &[u8], column: &[u8]) -> Option<usize> {
+    haystack.windows(column.len() + 1).rposition(|window| {
+        window.starts_with(column) && matches!(window[column.len()], b'=' | b'^')
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    const COLUMNS: [&str; 2] = ["numNewMsgs", "numMsgs"];
+
+    /// Parse a small Mork document made of the header, a dictionary of columns and `body` .
+    fn parse(body: &str) -> Result<FolderInfo> {
+        FolderInfo::parse(
+            format!(
+                "// <!-- <mdb:mork:z v=\"1.4\"/> -->\n\
+                 < <(a=c)> // (f=iso-8859-1)\n\
+                 (80=ns:msg:db:row:scope:dbfolderinfo:all)(81=numMsgs)(82=numNewMsgs)\n\
+                 (83=folderName)>\n\
+                 {{1:^80 {{(k^BF:c)(s=9)}} [1:^80(^81=10)(^82=4)(^83=Inbox)]}}\n\
+                 {body}"
+            )
+            .as_bytes(),
+        )
+    }
+
+    #[test]
+    fn parse_folder_info() {
+        let info = parse("").unwrap();
+        assert_eq!(info.number("numMsgs").unwrap(), 0x10);
+        assert_eq!(info.number("numNewMsgs").unwrap(), 4);
+        assert_eq!(info.get("folderName"), Some("Inbox"));
+        assert_eq!(info.number("expungedBytes").unwrap(), 0);
+    }
+
+    #[test]
+    fn parse_column_ids_from_dictionary() {
+        let document = b"// <!-- <mdb:mork:z v=\"1.4\"/> -->\n\
+                         < <(a=c)> (80=ns:msg:db:row:scope:dbfolderinfo:all)(A1=numNewMsgs)\n\
+                         (A2=numMsgs)>\n\
+                         {1:^80 [1:^80(^A2=20)(^a1=C)(^82=4)]}\n";
+        let info = FolderInfo::parse(document).unwrap();
+        assert_eq!(info.number("numNewMsgs").unwrap(), 0xC);
+        assert_eq!(info.number("numMsgs").unwrap(), 0x20);
+    }
+
+    #[test]
+    fn parse_escaped_value() {
+        let info = parse("@$${2{@[1:^80(^83=Caf$C3$A9 \\(1\\))]@$$}2}@\n").unwrap();
+        assert_eq!(info.get("folderName"), Some("Café (1)"));
+    }
+
+    #[test]
+    fn parse_cut_row() {
+        let info = parse("@$${2{@[-1:^80(^81=5)]@$$}2}@\n").unwrap();
+        assert_eq!(info.number("numMsgs").unwrap(), 5);
+        assert_eq!(info.number("numNewMsgs").unwrap(), 0);
+        assert_eq!(info.get("folderName"), None);
+    }
+
+    #[test]
+    fn parse_aborted_group() {
+        let info = parse(
+            "@$${2{@[1:^80(^82=7)]@$$}2}@\n\
+             @$${3{@[1:^80(^82=9)(^83=Trash)]@$$}~abort~3}@\n",
+        )
+        .unwrap();
+        assert_eq!(info.number("numNewMsgs").unwrap(), 7);
+        assert_eq!(info.get("folderName"), Some("Inbox"));
+    }
+
+    #[test]
+    fn parse_without_folder_info() {
+        let document = b"// <!-- <mdb:mork:z v=\"1.4\"/> -->\n\
+                         < <(a=c)> (84=ns:msg:db:row:scope:msgs:all)(85=subject)>\n\
+                         {1:^84 [2(^85=Hello)]}\n";
+        assert!(FolderInfo::parse(document).is_err());
+        assert!(FolderInfo::parse(b"Not a mailbox").is_err());
+    }
+
+    /// Build a Mork document larger than `SCAN_THRESHOLD` , which ends with `tail` .
+    fn document(tail: &str) -> Vec<u8> {
+        let mut document = String::from(
+            "// <!-- <mdb:mork:z v=\"1.4\"/> -->\n\
+             < <(a=c)> // (f=iso-8859-1)\n\
+             (80=ns:msg:db:row:scope:dbfolderinfo:all)(81=numMsgs)(82=numNewMsgs)\n\
+             (83=folderName)(84=ns:msg:db:row:scope:msgs:all)(85=subject)(86=flags)>\n\
+             {1:^80 {(k^BF:c)(s=9)} [1:^80(^81=10)(^82=4)(^83=Inbox)]}\n\
+             {1:^84 {(k^BF:c)(s=9)}\n",
+        );
+        let mut id = 2;
+        while document.len() as u64 <= SCAN_THRESHOLD {
+            document.push_str(&format!(
+                "[{id:X}(^85=Subject with [brackets] and (parens\\) {id})(^86=1)]\n"
+            ));
+            id += 1;
+        }
+        document.push_str("}\n@$${2{@[1:^80(^82=7)(^81=20)]@$$}2}@\n");
+        document.push_str(tail);
+        document.into_bytes()
+    }
+
+    /// Scan the document and compare the requested columns to a full parse. Returns if the
+    /// scan found them, or `None` if it would fall back to the full parse.
+    fn scan(document: &[u8], columns: &[&str]) -> Option<FolderInfo> {
+        let parsed = FolderInfo::parse(document).unwrap();
+        let scanned =
+            FolderInfo::scan(&mut Cursor::new(document), document.len() as u64, columns).unwrap();
+        if let Some(scanned) = &scanned {
+            for column in columns {
+                assert_eq!(scanned.get(column), parsed.get(column), "{column}");
+            }
+        }
+        scanned
+    }
+
+    #[test]
+    fn scan_last_update() {
+        let info = scan(&document(""), &COLUMNS).expect("scanned");
+        assert_eq!(info.number("numNewMsgs").unwrap(), 7);
+        assert_eq!(info.number("numMsgs").unwrap(), 0x20);
+        assert_eq!(info.get("folderName"), Some("Inbox"));
+    }
+
+    #[test]
+    fn scan_aborted_group() {
+        let document = document("@$${3{@[1:^80(^82=9)]@$$}~abort~3}@\n");
+        assert!(scan(&document, &COLUMNS).is_none());
+        assert_eq!(
+            FolderInfo::parse(&document)
+                .unwrap()
+                .number("numNewMsgs")
+                .unwrap(),
+            7
+        );
+    }
+
+    #[test]
+    fn scan_cut_row() {
+        let document = document("@$${3{@[-1:^80(^81=5)]@$$}3}@\n");
+        assert!(scan(&document, &["numNewMsgs"]).is_none());
+        let info = scan(&document, &["numMsgs"]).expect("scanned");
+        assert_eq!(info.number("numMsgs").unwrap(), 5);
+        assert_eq!(info.get("folderName"), None);
+    }
+
+    #[test]
+    fn scan_atom_value() {
+        let info = scan(
+            &document("@$${3{@[1:^80(^82^A0)]@$$}3}@\n<(A0=2B)>\n"),
+            &["numNewMsgs"],
+        )
+        .expect("scanned");
+        assert_eq!(info.number("numNewMsgs").unwrap(), 0x2B);
+
+        // The atom is defined before the row, outside of the scanned rest of the file.
+        let document = document("<(A0=2C)>\n@$${3{@[1:^80(^82^A0)]@$$}3}@\n");
+        assert!(scan(&document, &["numNewMsgs"]).is_none());
+        assert_eq!(
+            FolderInfo::parse(&document)
+                .unwrap()
+                .number("numNewMsgs")
+                .unwrap(),
+            0x2C
+        );
+    }
+
+    #[test]
+    fn scan_cell_across_chunks() {
+        // The last chunk read first starts two bytes into the cell. If the cell was missed, the
+        // aborted group before it would be found instead and the scan would give up.
+        let cell = "(^82=5)";
+        let end = "]@$$}4}@\n";
+        let padding = " ".repeat(CHUNK_SIZE + 2 - cell.len() - end.len());
+        let document = document(&format!(
+            "@$${{3{{@[1:^80(^82=9)]@$$}}~abort~3}}@\n@$${{4{{@[1:^80{cell}{end}{padding}"
+        ));
+        let start = find(&document, b"(^82=5)").unwrap();
+        assert_eq!(document.len() - start, CHUNK_SIZE + 2);
+
+        let info = scan(&document, &["numNewMsgs"]).expect("scanned");
+        assert_eq!(info.number("numNewMsgs").unwrap(), 5);
+    }
+}
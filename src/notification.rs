@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use notify_rust::Notification;
+use peepbird::Summary;
+
+/// Sends a desktop notification whenever new messages arrived since the last one.
+///
+/// Counts of each mailbox and host are remembered between the calls of `update` . An increase is
+/// only reported, once all increases together reach the `threshold` . Smaller increases are
+/// collected until then, while reading messages lowers the remembered counts again.
+#[derive(Debug)]
+pub struct Notifier {
+    threshold: u32,
+    /// Counts at the time of the last notification, by location. `None` before the first update.
+    seen: Option<HashMap<String, u32>>,
+}
+
+impl Notifier {
+    pub fn new(threshold: u32) -> Self {
+        Self {
+            threshold: threshold.max(1),
+            seen: None,
+        }
+    }
+
+    /// Compare the new counts to the remembered ones and notify about any new messages. The first
+    /// call only remembers the counts, so starting a watch does not notify about old messages.
+    pub fn update(&mut self, counts: &Summary) -> Result<()> {
+        let current: Vec<(String, &str, u32)> = counts
+            .mailboxes
+            .iter()
+            .map(|m| (m.file.display().to_string(), m.name.as_str(), m.count))
+            .chain(
+                counts
+                    .hosts
+                    .iter()
+                    .map(|h| (format!("{}:", h.host), h.host.as_str(), h.count)),
+            )
+            .collect();
+
+        let Some(seen) = self.seen.as_mut() else {
+            self.seen = Some(
+                current
+                    .into_iter()
+                    .map(|(key, _, count)| (key, count))
+                    .collect(),
+            );
+            return Ok(());
+        };
+
+        let mut increases = Vec::new();
+        for (key, name, count) in &current {
+            let last = seen.entry(key.clone()).or_insert(*count);
+            *last = (*last).min(*count);
+            if count > last {
+                increases.push((*name, count - *last));
+            }
+        }
+
+        let new: u32 = increases.iter().map(|(_, n)| n).sum();
+        if new < self.threshold {
+            return Ok(());
+        }
+        for (key, _, count) in current {
+            seen.insert(key, count);
+        }
+
+        let body: Vec<String> = increases
+            .iter()
+            .map(|(name, n)| format!("{} in {name}", new_messages(*n)))
+            .collect();
+
+        Notification::new()
+            .appname(env!("CARGO_PKG_NAME"))
+            .summary(&new_messages(new))
+            .body(&body.join("\n"))
+            .icon("mail-unread")
+            .show()
+            .context("Failed to send desktop notification")?;
+
+        Ok(())
+    }
+}
+
+/// Text like "3 new messages" for given number.
+fn new_messages(count: u32) -> String {
+    if count == 1 {
+        String::from("1 new message")
+    } else {
+        format!("{count} new messages")
+    }
+}
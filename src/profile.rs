@@ -0,0 +1,488 @@
+use std::{
+    collections::HashMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::discover::{self, Filter};
+use crate::mailbox::Mailbox;
+
+/// A section like `[Profile0]` in "profiles.ini" with all its keys and values.
+#[derive(Debug)]
+struct Section {
+    name: String,
+    entries: Vec<(String, String)>,
+}
+
+impl Section {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Parse an INI document into its sections. Empty lines and comments starting with `;` or `#`
+/// are ignored, as well as keys outside of any section.
+fn parse_ini(document: &str) -> Vec<Section> {
+    let mut sections: Vec<Section> = Vec::new();
+    for line in document.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            sections.push(Section {
+                name: name.trim().to_owned(),
+                entries: Vec::new(),
+            });
+        } else if let (Some(section), Some((key, value))) =
+            (sections.last_mut(), line.split_once('='))
+        {
+            section
+                .entries
+                .push((key.trim().to_owned(), value.trim().to_owned()));
+        }
+    }
+
+    sections
+}
+
+/// Thunderbird user data folders to search for "profiles.ini", in order of priority.
+///
+/// On Linux this includes the sandboxed Flatpak and Snap packages, which keep their data in
+/// separate folders.
+fn thunderbird_dirs() -> Vec<PathBuf> {
+    if cfg!(windows) {
+        return env::var_os("APPDATA")
+            .map(|appdata| PathBuf::from(appdata).join("Thunderbird"))
+            .into_iter()
+            .collect();
+    }
+
+    let Some(home) = env::var_os("HOME").map(PathBuf::from) else {
+        return Vec::new();
+    };
+    if cfg!(target_os = "macos") {
+        vec![home.join("Library/Thunderbird")]
+    } else {
+        vec![
+            home.join(".thunderbird"),
+            home.join(".var/app/org.mozilla.Thunderbird/.thunderbird"),
+            home.join("snap/thunderbird/common/.thunderbird"),
+        ]
+    }
+}
+
+/// Content of a "profiles.ini" file and the folder it's located in.
+struct ProfilesIni {
+    dir: PathBuf,
+    sections: Vec<Section>,
+}
+
+impl ProfilesIni {
+    fn read(dir: &Path) -> Result<Self> {
+        let file = dir.join("profiles.ini");
+        let document = fs::read_to_string(&file)
+            .with_context(|| format!("Failed to read profiles: {}", file.display()))?;
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            sections: parse_ini(&document),
+        })
+    }
+
+    fn profiles(&self) -> impl Iterator<Item = &Section> {
+        self.sections
+            .iter()
+            .filter(|s| s.name.starts_with("Profile"))
+    }
+
+    /// Full path of a profile section. Its `Path` is relative to the folder of "profiles.ini",
+    /// unless `IsRelative=0` .
+    fn profile_path(&self, profile: &Section) -> Option<PathBuf> {
+        let path = profile.get("Path")?;
+        if profile.get("IsRelative") == Some("0") {
+            Some(PathBuf::from(path))
+        } else {
+            Some(self.dir.join(path))
+        }
+    }
+
+    /// Lookup the default profile.
+    ///
+    /// Since Thunderbird 68 each installation remembers its own default profile in an
+    /// `[InstallXXXX]` section with a `Default` key, which takes priority. It holds the same
+    /// `Path` value as the profile section it belongs to. Older installations mark a profile
+    /// section with `Default=1` instead. As a last resort the first profile is used.
+    fn default_profile(&self) -> Option<PathBuf> {
+        let install_default = self
+            .sections
+            .iter()
+            .filter(|s| s.name.starts_with("Install"))
+            .find_map(|s| s.get("Default"));
+        if let Some(path) = install_default {
+            return match self.profiles().find(|p| p.get("Path") == Some(path)) {
+                Some(profile) => self.profile_path(profile),
+                None => Some(self.dir.join(path)),
+            };
+        }
+
+        self.profiles()
+            .find(|p| p.get("Default") == Some("1"))
+            .or_else(|| self.profiles().next())
+            .and_then(|p| self.profile_path(p))
+    }
+
+    /// Lookup a profile by the name it's shown with in Thunderbird's profile manager.
+    fn named_profile(&self, name: &str) -> Option<PathBuf> {
+        self.profiles()
+            .find(|p| p.get("Name") == Some(name))
+            .and_then(|p| self.profile_path(p))
+    }
+}
+
+/// Read all "profiles.ini" files found in the Thunderbird user data folders.
+fn read_all() -> Result<Vec<ProfilesIni>> {
+    let inis: Vec<ProfilesIni> = thunderbird_dirs()
+        .iter()
+        .filter(|dir| dir.join("profiles.ini").is_file())
+        .map(|dir| ProfilesIni::read(dir))
+        .collect::<Result<_>>()?;
+
+    if inis.is_empty() {
+        Err(anyhow!("Could not find any Thunderbird profiles.ini file."))
+    } else {
+        Ok(inis)
+    }
+}
+
+/// An account in a Thunderbird profile, such as an IMAP server or "Local Folders".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Account {
+    /// Local folder of the account, which contains all of its mailbox files.
+    pub dir: PathBuf,
+    /// Name of the account as shown in Thunderbird.
+    pub name: Option<String>,
+    /// Color of the account in Thunderbird like `#ff0000`, if the user has set one.
+    pub color: Option<String>,
+}
+
+/// A Thunderbird user profile folder, which contains the mailboxes of all accounts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    path: PathBuf,
+}
+
+impl Profile {
+    /// Use given folder as profile.
+    pub fn from_path(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Find the default Thunderbird profile folder of the current user.
+    ///
+    /// # Errors
+    ///
+    /// If no "profiles.ini" exists in any Thunderbird user data folder or none of them points
+    /// to a profile.
+    pub fn discover() -> Result<Self> {
+        read_all()?
+            .iter()
+            .find_map(ProfilesIni::default_profile)
+            .map(Self::from_path)
+            .ok_or_else(|| anyhow!("No default profile found in Thunderbird profiles.ini."))
+    }
+
+    /// Find a Thunderbird profile folder of the current user by its name.
+    ///
+    /// # Errors
+    ///
+    /// If no "profiles.ini" exists in any Thunderbird user data folder or none of them has a
+    /// profile with this name.
+    pub fn named(name: &str) -> Result<Self> {
+        read_all()?
+            .iter()
+            .find_map(|ini| ini.named_profile(name))
+            .map(Self::from_path)
+            .ok_or_else(|| {
+                anyhow!("No profile named \"{name}\" found in Thunderbird profiles.ini.")
+            })
+    }
+
+    /// Path to the profile folder.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Find all mailbox files of all accounts in the profile, see [`Filter`] . The resulting
+    /// paths are relative to the profile folder.
+    #[must_use]
+    pub fn mailboxes(&self, filter: &Filter) -> Vec<PathBuf> {
+        discover::discover(&self.path, filter)
+    }
+
+    /// Read all user preferences from "prefs.js" inside the profile folder.
+    ///
+    /// Each line of interest looks like `user_pref("mail.biff.show_badge", false);` . The key
+    /// name is the first quoted string, the value is everything after the comma up to the
+    /// closing `);` . String values get their surrounding quotes and escape backslashes removed,
+    /// all other values (numbers and booleans) are kept as is.
+    ///
+    /// # Errors
+    ///
+    /// If "prefs.js" cannot be read.
+    pub fn prefs(&self) -> Result<HashMap<String, String>> {
+        let file = self.path.join("prefs.js");
+        let document = fs::read_to_string(&file)
+            .with_context(|| format!("Failed to read preferences: {}", file.display()))?;
+
+        let mut prefs = HashMap::new();
+        for line in document.lines() {
+            let Some(pref) = line
+                .trim()
+                .strip_prefix("user_pref(\"")
+                .and_then(|l| l.strip_suffix(");"))
+            else {
+                continue;
+            };
+            if let Some((key, value)) = pref.split_once("\",") {
+                let value = value.trim();
+                let value = match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+                    Some(text) => {
+                        let mut unescaped = String::with_capacity(text.len());
+                        let mut chars = text.chars();
+                        while let Some(c) = chars.next() {
+                            unescaped.push(if c == '\\' {
+                                chars.next().unwrap_or(c)
+                            } else {
+                                c
+                            });
+                        }
+                        unescaped
+                    }
+                    None => value.to_owned(),
+                };
+                prefs.insert(key.to_owned(), value);
+            }
+        }
+
+        Ok(prefs)
+    }
+
+    /// Lookup the mailboxes Thunderbird itself sums up for its dock or taskbar badge.
+    ///
+    /// The accounts and their local directories are read from "prefs.js" of the profile. If the
+    /// badge is disabled with `mail.biff.show_badge`, then no mailbox is returned at all. With
+    /// `mail.notification.count.inbox_only` (enabled by default in Thunderbird) only the inbox of
+    /// each account is counted. Otherwise every folder counts, except "Trash" and "Junk", which
+    /// Thunderbird excludes from its badge as well. The virtual "smart mailboxes" are skipped to
+    /// not count the same mails twice.
+    ///
+    /// # Errors
+    ///
+    /// If "prefs.js" cannot be read.
+    pub fn badge_mailboxes(&self) -> Result<Vec<Mailbox>> {
+        let prefs = self.prefs()?;
+        let pref_enabled = |key: &str, default: bool| {
+            prefs
+                .get(key)
+                .map_or(default, |value| value.as_str() == "true")
+        };
+
+        let mut mailboxes = Vec::new();
+        if !pref_enabled("mail.biff.show_badge", true) {
+            return Ok(mailboxes);
+        }
+        let inbox_only = pref_enabled("mail.notification.count.inbox_only", true);
+
+        for (server, dir) in self.account_dirs(&prefs) {
+            let server_pref = |name: &str| prefs.get(&format!("mail.server.{server}.{name}"));
+
+            if server_pref("hostname").is_some_and(|host| host == "smart mailboxes") {
+                continue;
+            }
+
+            if inbox_only {
+                let inbox = Mailbox::from_path(&dir);
+                if inbox.file().is_file() {
+                    mailboxes.push(inbox);
+                }
+            } else {
+                let mut folders = Vec::new();
+                discover::find_mailbox_files(&dir, &mut folders);
+                mailboxes.extend(
+                    folders
+                        .into_iter()
+                        .filter(|f| {
+                            !f.file_stem()
+                                .is_some_and(|name| name == "Trash" || name == "Junk")
+                        })
+                        .map(Mailbox::from_path),
+                );
+            }
+        }
+
+        Ok(mailboxes)
+    }
+
+    /// Lookup all accounts of the profile in "prefs.js", see [`Account`] .
+    ///
+    /// The name and color users can give each account in Thunderbird's account settings are
+    /// read from `mail.server.<server>.name` and `mail.server.<server>.color` . The folder of
+    /// each account is resolved to its full path, so it can be compared to resolved mailbox
+    /// files.
+    ///
+    /// # Errors
+    ///
+    /// If "prefs.js" cannot be read.
+    pub fn accounts(&self) -> Result<Vec<Account>> {
+        let prefs = self.prefs()?;
+
+        Ok(self
+            .account_dirs(&prefs)
+            .into_iter()
+            .map(|(server, dir)| {
+                let server_pref = |name: &str| prefs.get(&format!("mail.server.{server}.{name}"));
+                Account {
+                    dir: dir.canonicalize().unwrap_or(dir),
+                    name: server_pref("name").cloned(),
+                    color: server_pref("color").cloned(),
+                }
+            })
+            .collect())
+    }
+
+    /// Server name and local directory of each account listed in the preferences. Accounts
+    /// without a server or directory are left out.
+    fn account_dirs(&self, prefs: &HashMap<String, String>) -> Vec<(String, PathBuf)> {
+        let accounts = prefs
+            .get("mail.accountmanager.accounts")
+            .map(String::as_str)
+            .unwrap_or_default();
+
+        accounts
+            .split(',')
+            .map(str::trim)
+            .filter_map(|account| {
+                let server = prefs.get(&format!("mail.account.{account}.server"))?;
+                let server_pref = |name: &str| prefs.get(&format!("mail.server.{server}.{name}"));
+                let dir = if let Some(dir) =
+                    server_pref("directory-rel").and_then(|dir| dir.strip_prefix("[ProfD]"))
+                {
+                    self.path.join(dir)
+                } else {
+                    PathBuf::from(server_pref("directory")?)
+                };
+
+                Some((server.clone(), dir))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse a "profiles.ini" document as if located in `/home/user/.thunderbird` .
+    fn profiles_ini(document: &str) -> ProfilesIni {
+        ProfilesIni {
+            dir: PathBuf::from("/home/user/.thunderbird"),
+            sections: parse_ini(document),
+        }
+    }
+
+    const PROFILES: &str = "\
+        [General]\n\
+        StartWithLastProfile=1\n\
+        \n\
+        ; Comment\n\
+        [Profile0]\n\
+        Name=default\n\
+        IsRelative=1\n\
+        Path=abcd1234.default\n\
+        Default=1\n\
+        \n\
+        [Profile1]\n\
+        Name = work\n\
+        IsRelative = 0\n\
+        Path = /mnt/work/thunderbird\n\
+        \n\
+        [Profile2]\n\
+        Name=default-release\n\
+        IsRelative=1\n\
+        Path=efgh5678.default-release\n";
+
+    #[test]
+    fn parse_ini_sections() {
+        let sections = parse_ini(PROFILES);
+        let names: Vec<&str> = sections.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["General", "Profile0", "Profile1", "Profile2"]);
+        assert_eq!(sections[2].get("Name"), Some("work"));
+        assert_eq!(sections[2].get("Path"), Some("/mnt/work/thunderbird"));
+        assert_eq!(sections[0].get("Name"), None);
+    }
+
+    #[test]
+    fn default_profile_marked() {
+        let ini = profiles_ini(PROFILES);
+        assert_eq!(
+            ini.default_profile(),
+            Some(PathBuf::from("/home/user/.thunderbird/abcd1234.default"))
+        );
+    }
+
+    #[test]
+    fn default_profile_of_install() {
+        let ini = profiles_ini(&format!(
+            "{PROFILES}\n[Install4F96D1932A9F858E]\nDefault=efgh5678.default-release\nLocked=1\n"
+        ));
+        assert_eq!(
+            ini.default_profile(),
+            Some(PathBuf::from(
+                "/home/user/.thunderbird/efgh5678.default-release"
+            ))
+        );
+
+        let ini = profiles_ini(&format!(
+            "{PROFILES}\n[Install4F96D1932A9F858E]\nDefault=/mnt/work/thunderbird\n"
+        ));
+        assert_eq!(
+            ini.default_profile(),
+            Some(PathBuf::from("/mnt/work/thunderbird"))
+        );
+    }
+
+    #[test]
+    fn default_profile_first() {
+        let ini = profiles_ini(&PROFILES.replace("Default=1\n", ""));
+        assert_eq!(
+            ini.default_profile(),
+            Some(PathBuf::from("/home/user/.thunderbird/abcd1234.default"))
+        );
+        assert_eq!(
+            profiles_ini("[General]\nVersion=2\n").default_profile(),
+            None
+        );
+    }
+
+    #[test]
+    fn named_profile() {
+        let ini = profiles_ini(PROFILES);
+        assert_eq!(
+            ini.named_profile("work"),
+            Some(PathBuf::from("/mnt/work/thunderbird"))
+        );
+        assert_eq!(
+            ini.named_profile("default-release"),
+            Some(PathBuf::from(
+                "/home/user/.thunderbird/efgh5678.default-release"
+            ))
+        );
+        assert_eq!(ini.named_profile("missing"), None);
+    }
+}
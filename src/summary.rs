@@ -0,0 +1,244 @@
+use std::{path::PathBuf, process::Command};
+
+use anyhow::{anyhow, Context, Result};
+use serde_derive::Serialize;
+use serde_json::{json, Value};
+
+use crate::{
+    mailbox::{Field, Mailbox},
+    mork::FolderInfo,
+    profile::Account,
+};
+
+/// Count of a single mailbox file.
+#[derive(Serialize, Debug, Clone)]
+pub struct MailboxCount {
+    /// Input path as given by the user.
+    pub path: PathBuf,
+    /// Resolved path to the .msf-file.
+    pub file: PathBuf,
+    /// Name of the folder as shown in Thunderbird.
+    pub name: String,
+    /// Name of the account the mailbox belongs to, see `Summary::set_accounts` .
+    pub account_name: Option<String>,
+    /// Color of the account in Thunderbird like `#ff0000`, if the user has set one.
+    pub account_color: Option<String>,
+    pub count: u32,
+}
+
+/// Total count of a remote machine.
+#[derive(Serialize, Debug, Clone)]
+pub struct HostCount {
+    pub host: String,
+    pub count: u32,
+}
+
+/// Version of the JSON document of `Summary` . It is increased whenever a field is renamed,
+/// removed or changes its meaning, but not for new fields.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Result of counting multiple mailboxes and hosts, also used as the document for JSON output.
+#[derive(Serialize, Debug, Clone)]
+pub struct Summary {
+    /// Version of the document layout, see `SCHEMA_VERSION` .
+    pub schema_version: u32,
+    /// Kind of messages that are counted.
+    pub field: Field,
+    /// Sum of all individual counts.
+    pub total: u32,
+    pub mailboxes: Vec<MailboxCount>,
+    pub hosts: Vec<HostCount>,
+    /// Accounts to find the account of each added mailbox in.
+    #[serde(skip)]
+    accounts: Vec<Account>,
+}
+
+impl MailboxCount {
+    /// Count messages of given kind in a mailbox. The `path` is the location of the mailbox as
+    /// given by the user, which may differ from the resolved file of the mailbox. The folder
+    /// name is taken from the mailbox file, if Thunderbird stored it there.
+    ///
+    /// # Errors
+    ///
+    /// If the mailbox file cannot be read or parsed.
+    pub fn read(path: PathBuf, mailbox: &Mailbox, field: Field) -> Result<Self> {
+        let info = FolderInfo::read_columns(mailbox.file(), &[field.column()])?;
+        let count = info
+            .number(field.column())
+            .with_context(|| format!("Failed to count mailbox: {}", mailbox.file().display()))?;
+        let name = info
+            .get("folderName")
+            .map_or_else(|| mailbox.name(), str::to_owned);
+
+        Ok(Self {
+            path,
+            file: mailbox.file().to_path_buf(),
+            name,
+            account_name: None,
+            account_color: None,
+            count,
+        })
+    }
+}
+
+impl HostCount {
+    /// Query peepbird on a remote machine for its total count of messages of given kind.
+    ///
+    /// The remote `peepbird` is started with `ssh` and uses the user configuration of that
+    /// machine for its mailboxes. Options that would change its output are overwritten, so it
+    /// prints the counts once as JSON document. A total count outside of the range of the remote
+    /// `min` and `max` options is not a failure of the host.
+    ///
+    /// # Errors
+    ///
+    /// If `ssh` or the remote `peepbird` fails, or its output is not a valid JSON document.
+    pub fn query(host: &str, field: Field) -> Result<Self> {
+        let output = Command::new("ssh")
+            .args(["-o", "BatchMode=yes", "--", host])
+            .arg(format!("peepbird --once --format json --field {field}"))
+            .output()
+            .context("Failed to run ssh")?;
+
+        // Exit code 3 is a total count outside of the range of `min` and `max` .
+        if !matches!(output.status.code(), Some(0 | 3)) {
+            return Err(anyhow!(
+                "Failed to query remote host: {host}: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let document = stdout.lines().last().unwrap_or_default();
+        let count = serde_json::from_str::<serde_json::Value>(document)
+            .ok()
+            .and_then(|summary| summary.get("total")?.as_u64())
+            .and_then(|total| u32::try_from(total).ok())
+            .ok_or_else(|| anyhow!("Unexpected output from remote host: {host}: {document}"))?;
+
+        Ok(Self {
+            host: host.to_owned(),
+            count,
+        })
+    }
+}
+
+impl Default for Summary {
+    fn default() -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            field: Field::default(),
+            total: 0,
+            mailboxes: Vec::new(),
+            hosts: Vec::new(),
+            accounts: Vec::new(),
+        }
+    }
+}
+
+impl Summary {
+    /// Create an empty summary for counting messages of given kind.
+    #[must_use]
+    pub fn new(field: Field) -> Self {
+        Self {
+            field,
+            ..Default::default()
+        }
+    }
+
+    /// JSON Schema describing the JSON document of a summary.
+    #[must_use]
+    pub fn schema() -> Value {
+        let count = json!({ "type": "integer", "minimum": 0 });
+
+        json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "title": "peepbird summary",
+            "description": "Counts of messages in Thunderbird mailboxes and on remote hosts.",
+            "type": "object",
+            "required": ["schema_version", "field", "total", "mailboxes", "hosts"],
+            "properties": {
+                "schema_version": { "const": SCHEMA_VERSION },
+                "field": { "enum": ["unread", "total"] },
+                "total": count,
+                "mailboxes": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "required": ["path", "file", "name", "count"],
+                        "properties": {
+                            "path": { "type": "string" },
+                            "file": { "type": "string" },
+                            "name": { "type": "string" },
+                            "account_name": { "type": ["string", "null"] },
+                            "account_color": { "type": ["string", "null"] },
+                            "count": count,
+                        },
+                    },
+                },
+                "hosts": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "required": ["host", "count"],
+                        "properties": {
+                            "host": { "type": "string" },
+                            "count": count,
+                        },
+                    },
+                },
+            },
+        })
+    }
+
+    /// Count messages in a mailbox and add it to the total. See `MailboxCount::read` .
+    ///
+    /// # Errors
+    ///
+    /// If the mailbox file cannot be read or parsed.
+    pub fn add_mailbox(&mut self, path: PathBuf, mailbox: &Mailbox) -> Result<u32> {
+        let mailbox = MailboxCount::read(path, mailbox, self.field)?;
+        Ok(self.push_mailbox(mailbox))
+    }
+
+    /// Query peepbird on a remote machine and add its count to the total. See
+    /// `HostCount::query` .
+    ///
+    /// # Errors
+    ///
+    /// If `ssh` or the remote `peepbird` fails, or its output is not a valid JSON document.
+    pub fn add_host(&mut self, host: &str) -> Result<u32> {
+        let host = HostCount::query(host, self.field)?;
+        Ok(self.push_host(host))
+    }
+
+    /// Use these accounts to set the name and color of the account for each mailbox added from
+    /// now on. The account of a mailbox is the one, whose folder contains the mailbox file.
+    pub fn set_accounts(&mut self, accounts: Vec<Account>) {
+        self.accounts = accounts;
+    }
+
+    /// Add an already read mailbox count to the total, which allows reading multiple mailboxes
+    /// at the same time. Returns the count.
+    pub fn push_mailbox(&mut self, mut mailbox: MailboxCount) -> u32 {
+        if let Some(account) = self
+            .accounts
+            .iter()
+            .find(|a| mailbox.file.starts_with(&a.dir))
+        {
+            mailbox.account_name.clone_from(&account.name);
+            mailbox.account_color.clone_from(&account.color);
+        }
+        let count = mailbox.count;
+        self.total += count;
+        self.mailboxes.push(mailbox);
+        count
+    }
+
+    /// Add an already queried host count to the total. Returns the count.
+    pub fn push_host(&mut self, host: HostCount) -> u32 {
+        let count = host.count;
+        self.total += count;
+        self.hosts.push(host);
+        count
+    }
+}
@@ -0,0 +1,40 @@
+/// Replace each placeholder like `{count}` in `template` by its value.
+///
+/// The `value` function is asked for the value of each placeholder name. Placeholders it does not
+/// know about are kept as they are, so a typo stays visible in the output. Use `{{` and `}}` for
+/// literal braces.
+pub fn render<F>(template: &str, value: F) -> String
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find(['{', '}']) {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            output.push_str(&rest[..1]);
+            rest = &rest[2..];
+        } else if let Some(end) = rest.starts_with('{').then(|| rest.find('}')).flatten() {
+            let name = &rest[1..end];
+            match value(name) {
+                Some(text) => output.push_str(&text),
+                None => output.push_str(&rest[..=end]),
+            }
+            rest = &rest[end + 1..];
+        } else {
+            output.push_str(&rest[..1]);
+            rest = &rest[1..];
+        }
+    }
+    output.push_str(rest);
+
+    output
+}
+
+/// Escape all braces in `text` , so it's rendered as is when used within a template.
+pub fn escape(text: &str) -> String {
+    text.replace('{', "{{").replace('}', "}}")
+}
@@ -0,0 +1,126 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, RecvTimeoutError},
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Context, Result};
+use notify::{EventKind, RecursiveMode, Watcher};
+
+/// Time to wait for more changes after the first one, before the counts are emitted again.
+///
+/// Thunderbird usually writes a mailbox file in several small steps in a row. Collecting them
+/// avoids printing multiple lines for what is actually a single update.
+const SETTLE_TIME: Duration = Duration::from_millis(200);
+
+/// Longest time to wait for more changes, so a file that is written all the time still gets its
+/// counts emitted regularly.
+const MAX_SETTLE_TIME: Duration = Duration::from_secs(1);
+
+/// Name of the file in a profile directory, which lists all accounts and their settings.
+const PREFS_FILENAME: &str = "prefs.js";
+
+/// Run `emit` once and then every time one of the mailbox files has changed.
+///
+/// Instead of the mailbox files themselves, their parent directories are watched. Thunderbird may
+/// replace an .msf-file by writing a new one and renaming it, which would silently end a watch on
+/// the old file. If `interval` is given, then `emit` is also run whenever that much time passed
+/// without any change, which allows to include sources that cannot be watched, like remote hosts.
+/// This function only returns on error.
+///
+/// If a `profile` directory is given, it's watched with all of its subdirectories. Then a change
+/// of any .msf-file or the account settings in it counts too, so new mailboxes can be picked up.
+pub fn watch<F>(
+    mailboxes: &[PathBuf],
+    profile: Option<&Path>,
+    interval: Option<Duration>,
+    mut emit: F,
+) -> Result<()>
+where
+    F: FnMut(),
+{
+    let (tx, rx) = mpsc::channel();
+    let mut watcher =
+        notify::recommended_watcher(tx).context("Failed to initialize file watcher.")?;
+
+    // Events report canonical paths, as mailbox files are already resolved to.
+    let profile = match profile {
+        Some(dir) => {
+            let dir = dir
+                .canonicalize()
+                .with_context(|| format!("Failed to watch profile: {}", dir.display()))?;
+            watcher
+                .watch(&dir, RecursiveMode::Recursive)
+                .with_context(|| format!("Failed to watch profile: {}", dir.display()))?;
+            Some(dir)
+        }
+        None => None,
+    };
+
+    let mailboxes: HashSet<&Path> = mailboxes.iter().map(PathBuf::as_path).collect();
+    let folders: HashSet<&Path> = mailboxes
+        .iter()
+        .filter_map(|f| f.parent())
+        .filter(|folder| !profile.as_ref().is_some_and(|dir| folder.starts_with(dir)))
+        .collect();
+    for folder in folders {
+        watcher
+            .watch(folder, RecursiveMode::NonRecursive)
+            .with_context(|| format!("Failed to watch mailbox folder: {}", folder.display()))?;
+    }
+
+    let is_profile_change = |path: &Path| {
+        profile.as_ref().is_some_and(|dir| path.starts_with(dir))
+            && (path.extension().is_some_and(|ext| ext == "msf")
+                || path.file_name().is_some_and(|name| name == PREFS_FILENAME))
+    };
+    let is_mailbox_change = |event: &notify::Event| {
+        !matches!(event.kind, EventKind::Access(_))
+            && event
+                .paths
+                .iter()
+                .any(|p| mailboxes.contains(p.as_path()) || is_profile_change(p))
+    };
+
+    emit();
+    // Ignored events must not restart the interval, so it's tracked as point in time.
+    let mut next_emit = interval.map(|duration| Instant::now() + duration);
+    loop {
+        let received = match next_emit {
+            Some(deadline) => rx.recv_timeout(deadline.saturating_duration_since(Instant::now())),
+            None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+
+        match received {
+            Ok(Ok(event)) => {
+                if !is_mailbox_change(&event) {
+                    continue;
+                }
+                // Only further changes of mailboxes extend the wait, up to its maximum.
+                let latest = Instant::now() + MAX_SETTLE_TIME;
+                let mut settled = Instant::now() + SETTLE_TIME;
+                while let Ok(event) = rx.recv_timeout(
+                    settled
+                        .min(latest)
+                        .saturating_duration_since(Instant::now()),
+                ) {
+                    let event = event.context("Failed to watch mailbox files.")?;
+                    if is_mailbox_change(&event) {
+                        settled = Instant::now() + SETTLE_TIME;
+                    }
+                }
+                emit();
+                next_emit = interval.map(|duration| Instant::now() + duration);
+            }
+            Ok(Err(e)) => return Err(e).context("Failed to watch mailbox files."),
+            Err(RecvTimeoutError::Timeout) => {
+                emit();
+                next_emit = interval.map(|duration| Instant::now() + duration);
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                return Err(anyhow!("File watcher stopped unexpectedly."))
+            }
+        }
+    }
+}